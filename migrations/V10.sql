@@ -0,0 +1,11 @@
+CREATE TABLE username_history
+(
+    id           BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    user_id      BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+--  The username the user had before the change.
+    username     TEXT   NOT NULL,
+    time_changed BIGINT NOT NULL DEFAULT extract(EPOCH FROM now())
+);
+
+CREATE INDEX username_history_user_id_index ON username_history (user_id);
+CREATE INDEX username_history_username_index ON username_history (lower(username));
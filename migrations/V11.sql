@@ -0,0 +1,18 @@
+ALTER TABLE users
+    ADD COLUMN role              TEXT NOT NULL DEFAULT 'user' CHECK (role IN ('user', 'moderator', 'admin')),
+    ADD COLUMN time_suspended    BIGINT,
+    ADD COLUMN suspension_reason TEXT;
+
+CREATE TABLE admin_actions
+(
+    id              BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    actor_id        BIGINT REFERENCES users (id) ON DELETE SET NULL,
+    action          TEXT   NOT NULL,
+--  No foreign keys, the trail must outlive whatever the action was about.
+    target_user_id  BIGINT,
+    target_tweet_id BIGINT,
+    details         TEXT,
+    time_created    BIGINT NOT NULL DEFAULT extract(EPOCH FROM now())
+);
+
+CREATE INDEX admin_actions_target_user_id_index ON admin_actions (target_user_id);
@@ -0,0 +1,58 @@
+CREATE TABLE audit_events
+(
+    id              BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+--  No foreign keys, the trail must outlive the users and tweets it is about.
+    actor_id        BIGINT,
+    action          TEXT   NOT NULL,
+    target_user_id  BIGINT,
+    target_tweet_id BIGINT,
+    details         TEXT,
+    ip_address      INET,
+    user_agent      TEXT,
+    request_id      TEXT,
+    time_created    BIGINT NOT NULL DEFAULT extract(EPOCH FROM now())
+);
+
+CREATE INDEX audit_events_actor_id_index ON audit_events (actor_id, time_created);
+CREATE INDEX audit_events_target_user_id_index ON audit_events (target_user_id, time_created);
+CREATE INDEX audit_events_time_created_index ON audit_events (time_created);
+
+-- a lockout is caused by whoever guessed the password, not by the user
+INSERT INTO audit_events (actor_id, action, target_user_id, target_tweet_id, details, ip_address, user_agent, time_created)
+SELECT actor_id, action, target_user_id, target_tweet_id, details, ip_address, user_agent, time_created
+FROM (SELECT CASE WHEN action = 'account_locked' THEN NULL ELSE user_id END AS actor_id,
+             action,
+             user_id                                                        AS target_user_id,
+             NULL::BIGINT                                                   AS target_tweet_id,
+             NULL                                                           AS details,
+             ip_address,
+             user_agent,
+             time_created
+      FROM security_events
+      UNION ALL
+      SELECT actor_id, action, target_user_id, target_tweet_id, details, NULL, NULL, time_created
+      FROM admin_actions) AS events
+ORDER BY time_created;
+
+DROP TABLE security_events;
+DROP TABLE admin_actions;
+
+CREATE FUNCTION reject_audit_event_change()
+    RETURNS TRIGGER AS
+$$
+BEGIN
+    RAISE EXCEPTION 'audit_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER reject_audit_event_change
+    BEFORE UPDATE OR DELETE
+    ON audit_events
+    FOR EACH ROW
+EXECUTE FUNCTION reject_audit_event_change();
+
+CREATE TRIGGER reject_audit_event_truncate
+    BEFORE TRUNCATE
+    ON audit_events
+    FOR EACH STATEMENT
+EXECUTE FUNCTION reject_audit_event_change();
@@ -0,0 +1,21 @@
+CREATE TABLE invites
+(
+    id           BIGINT  NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+--  Kept in plain text, so that users can look them up again to pass them on.
+    code         TEXT    NOT NULL UNIQUE,
+    creator_id   BIGINT  NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    max_uses     INTEGER NOT NULL,
+    use_count    INTEGER NOT NULL DEFAULT 0,
+    time_expires BIGINT  NOT NULL,
+    time_created BIGINT  NOT NULL DEFAULT extract(EPOCH FROM now())
+);
+
+CREATE INDEX invites_creator_id_index ON invites (creator_id, time_created);
+
+ALTER TABLE users
+--  Overrides INVITE_QUOTA for a single user.
+    ADD COLUMN invite_quota INTEGER,
+    ADD COLUMN invited_by   BIGINT REFERENCES users (id) ON DELETE SET NULL,
+    ADD COLUMN invite_id    BIGINT REFERENCES invites (id) ON DELETE SET NULL;
+
+CREATE INDEX users_invited_by_index ON users (invited_by) WHERE invited_by IS NOT NULL;
@@ -0,0 +1,9 @@
+-- Challenges themselves are signed and never stored, only the ones that were solved are
+-- remembered until they expire, so that a solution can't be used twice.
+CREATE TABLE spent_challenges
+(
+    nonce        TEXT   NOT NULL PRIMARY KEY,
+    time_expires BIGINT NOT NULL
+);
+
+CREATE INDEX spent_challenges_time_expires_index ON spent_challenges (time_expires);
@@ -0,0 +1,16 @@
+ALTER TABLE users
+    ADD COLUMN display_name TEXT,
+    ADD COLUMN bio          TEXT,
+    ADD COLUMN location     TEXT,
+    ADD COLUMN website      TEXT,
+    ADD COLUMN avatar       TEXT,
+    ADD COLUMN time_created BIGINT NOT NULL DEFAULT extract(EPOCH FROM now());
+
+-- accounts from before the audit trail keep the time of this migration
+UPDATE users
+SET time_created = events.time_created
+FROM (SELECT target_user_id, min(time_created) AS time_created
+      FROM audit_events
+      WHERE action = 'user_created'
+      GROUP BY target_user_id) AS events
+WHERE users.id = events.target_user_id;
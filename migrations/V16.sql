@@ -0,0 +1,6 @@
+-- likes from before this migration all get its time
+ALTER TABLE user_liked_tweets
+    ADD COLUMN time_created BIGINT NOT NULL DEFAULT extract(EPOCH FROM now());
+
+CREATE INDEX user_liked_tweets_user_id_index ON user_liked_tweets (user_id, time_created DESC, tweet_id DESC);
+CREATE INDEX user_liked_tweets_tweet_id_index ON user_liked_tweets (tweet_id, time_created DESC, user_id DESC);
@@ -0,0 +1,37 @@
+CREATE TABLE follows
+(
+    follower_id  BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    followee_id  BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    time_created BIGINT NOT NULL DEFAULT extract(EPOCH FROM now()),
+    PRIMARY KEY (follower_id, followee_id),
+    CHECK (follower_id <> followee_id)
+);
+
+CREATE INDEX follows_follower_id_index ON follows (follower_id, time_created DESC, followee_id DESC);
+CREATE INDEX follows_followee_id_index ON follows (followee_id, time_created DESC, follower_id DESC);
+
+ALTER TABLE users
+    ADD COLUMN follower_count  INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN following_count INTEGER NOT NULL DEFAULT 0;
+
+CREATE FUNCTION update_follow_counts()
+    RETURNS TRIGGER AS
+$$
+BEGIN
+    IF tg_op = 'INSERT' THEN
+        UPDATE users SET following_count = following_count + 1 WHERE id = new.follower_id;
+        UPDATE users SET follower_count = follower_count + 1 WHERE id = new.followee_id;
+        RETURN new;
+    ELSE
+        UPDATE users SET following_count = following_count - 1 WHERE id = old.follower_id;
+        UPDATE users SET follower_count = follower_count - 1 WHERE id = old.followee_id;
+        RETURN old;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER update_follow_counts
+    AFTER INSERT OR DELETE
+    ON follows
+    FOR EACH ROW
+EXECUTE FUNCTION update_follow_counts();
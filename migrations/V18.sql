@@ -0,0 +1,42 @@
+-- tweets that weren't copied into the timelines of the followers are merged in when reading
+ALTER TABLE tweets
+    ADD COLUMN fanned_out BOOLEAN NOT NULL DEFAULT false;
+
+CREATE INDEX tweets_not_fanned_out_index ON tweets (user_id, time_created DESC, id DESC) WHERE NOT fanned_out;
+
+CREATE TABLE timelines
+(
+    user_id      BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    tweet_id     BIGINT NOT NULL REFERENCES tweets (id) ON DELETE CASCADE,
+--  The time of the tweet, so that timelines can be read without touching the tweets.
+    time_created BIGINT NOT NULL,
+    PRIMARY KEY (user_id, tweet_id)
+);
+
+CREATE INDEX timelines_user_id_index ON timelines (user_id, time_created DESC, tweet_id DESC);
+CREATE INDEX timelines_tweet_id_index ON timelines (tweet_id);
+
+-- existing tweets are copied like new ones, otherwise they would be merged in on every read forever.
+-- Like a new follow, that brings in the latest 1000 tweets of every author, the older ones are only marked.
+-- 10000 is the default TIMELINE_FANOUT_LIMIT, the tweets of larger accounts are still merged in on read
+WITH latest AS (SELECT tweets.id, tweets.user_id, tweets.time_created
+                FROM (SELECT id,
+                             user_id,
+                             time_created,
+                             row_number() OVER (PARTITION BY user_id ORDER BY time_created DESC, id DESC) AS n
+                      FROM tweets) AS tweets
+                         JOIN users ON users.id = tweets.user_id
+                WHERE n <= 1000
+                  AND users.follower_count <= 10000)
+INSERT
+INTO timelines (user_id, tweet_id, time_created)
+SELECT follows.follower_id, latest.id, latest.time_created
+FROM latest
+         JOIN follows ON follows.followee_id = latest.user_id
+UNION ALL
+SELECT user_id, id, time_created
+FROM latest;
+
+UPDATE tweets
+SET fanned_out = true
+WHERE user_id IN (SELECT id FROM users WHERE follower_count <= 10000);
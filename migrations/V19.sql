@@ -0,0 +1,50 @@
+-- Replies outlive the tweets they reply to. They stay in the conversation, but lose their parent.
+ALTER TABLE tweets
+    ADD COLUMN in_reply_to_tweet_id BIGINT REFERENCES tweets (id) ON DELETE SET NULL,
+    ADD COLUMN conversation_id      BIGINT,
+    ADD COLUMN reply_count          INTEGER NOT NULL DEFAULT 0;
+
+UPDATE tweets
+SET conversation_id = id;
+
+ALTER TABLE tweets
+    ALTER COLUMN conversation_id SET NOT NULL;
+
+CREATE INDEX tweets_in_reply_to_tweet_id_index ON tweets (in_reply_to_tweet_id, time_created DESC, id DESC);
+
+-- a tweet starts a conversation, unless it replies to one
+CREATE FUNCTION set_conversation_id()
+    RETURNS TRIGGER AS
+$$
+BEGIN
+    new.conversation_id := coalesce((SELECT conversation_id FROM tweets WHERE id = new.in_reply_to_tweet_id),
+                                    new.id);
+    RETURN new;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER set_conversation_id
+    BEFORE INSERT
+    ON tweets
+    FOR EACH ROW
+EXECUTE FUNCTION set_conversation_id();
+
+CREATE FUNCTION update_reply_count()
+    RETURNS TRIGGER AS
+$$
+BEGIN
+    IF tg_op = 'INSERT' THEN
+        UPDATE tweets SET reply_count = reply_count + 1 WHERE id = new.in_reply_to_tweet_id;
+        RETURN new;
+    ELSE
+        UPDATE tweets SET reply_count = reply_count - 1 WHERE id = old.in_reply_to_tweet_id;
+        RETURN old;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER update_reply_count
+    AFTER INSERT OR DELETE
+    ON tweets
+    FOR EACH ROW
+EXECUTE FUNCTION update_reply_count();
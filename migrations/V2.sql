@@ -0,0 +1,6 @@
+ALTER TABLE sessions
+    ADD COLUMN user_agent    TEXT,
+    ADD COLUMN ip_address    INET,
+    ADD COLUMN tls_version   TEXT,
+    ADD COLUMN alpn_protocol TEXT,
+    ADD COLUMN time_created  BIGINT NOT NULL DEFAULT extract(EPOCH FROM now());
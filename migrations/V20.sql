@@ -0,0 +1,58 @@
+CREATE TABLE retweets
+(
+    user_id      BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    tweet_id     BIGINT NOT NULL REFERENCES tweets (id) ON DELETE CASCADE,
+    time_created BIGINT NOT NULL DEFAULT extract(EPOCH FROM now()),
+    PRIMARY KEY (user_id, tweet_id)
+);
+
+CREATE INDEX retweets_user_id_index ON retweets (user_id, time_created DESC);
+CREATE INDEX retweets_tweet_id_index ON retweets (tweet_id);
+
+-- Quotes outlive the tweets they quote, like replies.
+ALTER TABLE tweets
+    ADD COLUMN quoted_tweet_id BIGINT REFERENCES tweets (id) ON DELETE SET NULL,
+    ADD COLUMN retweet_count   INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN quote_count     INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX tweets_quoted_tweet_id_index ON tweets (quoted_tweet_id);
+
+CREATE FUNCTION update_retweet_count()
+    RETURNS TRIGGER AS
+$$
+BEGIN
+    IF tg_op = 'INSERT' THEN
+        UPDATE tweets SET retweet_count = retweet_count + 1 WHERE id = new.tweet_id;
+        RETURN new;
+    ELSE
+        UPDATE tweets SET retweet_count = retweet_count - 1 WHERE id = old.tweet_id;
+        RETURN old;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER update_retweet_count
+    AFTER INSERT OR DELETE
+    ON retweets
+    FOR EACH ROW
+EXECUTE FUNCTION update_retweet_count();
+
+CREATE FUNCTION update_quote_count()
+    RETURNS TRIGGER AS
+$$
+BEGIN
+    IF tg_op = 'INSERT' THEN
+        UPDATE tweets SET quote_count = quote_count + 1 WHERE id = new.quoted_tweet_id;
+        RETURN new;
+    ELSE
+        UPDATE tweets SET quote_count = quote_count - 1 WHERE id = old.quoted_tweet_id;
+        RETURN old;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER update_quote_count
+    AFTER INSERT OR DELETE
+    ON tweets
+    FOR EACH ROW
+EXECUTE FUNCTION update_quote_count();
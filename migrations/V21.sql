@@ -0,0 +1,24 @@
+CREATE TABLE tweet_entities
+(
+    tweet_id    BIGINT  NOT NULL REFERENCES tweets (id) ON DELETE CASCADE,
+    kind        TEXT    NOT NULL,
+--  In characters, the end is exclusive.
+    start_index INTEGER NOT NULL,
+    end_index   INTEGER NOT NULL,
+    value       TEXT    NOT NULL,
+--  Only set on mentions, which are removed along with the user.
+    user_id     BIGINT REFERENCES users (id) ON DELETE CASCADE,
+    PRIMARY KEY (tweet_id, start_index)
+);
+
+CREATE INDEX tweet_entities_user_id_index ON tweet_entities (user_id) WHERE user_id IS NOT NULL;
+CREATE INDEX tweet_entities_hashtag_index ON tweet_entities (lower(value)) WHERE kind = 'hashtag';
+
+-- the entities of the existing tweets are extracted in the background, new ones come with theirs
+ALTER TABLE tweets
+    ADD COLUMN entities_extracted BOOLEAN NOT NULL DEFAULT false;
+
+ALTER TABLE tweets
+    ALTER COLUMN entities_extracted SET DEFAULT true;
+
+CREATE INDEX tweets_entities_not_extracted_index ON tweets (id) WHERE NOT entities_extracted;
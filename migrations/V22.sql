@@ -0,0 +1,6 @@
+-- Kept up to date by Postgres on every insert and update. Hashtags and mentions are searched for
+-- through tweet_entities instead, the parser would strip their signs anyway.
+ALTER TABLE tweets
+    ADD COLUMN text_search tsvector GENERATED ALWAYS AS (to_tsvector('english', text)) STORED;
+
+CREATE INDEX tweets_text_search_index ON tweets USING GIN (text_search);
@@ -0,0 +1,4 @@
+-- Unverified addresses don't block anyone, an address belongs to the first user who verifies it.
+DROP INDEX users_email_index;
+
+CREATE UNIQUE INDEX users_email_index ON users (lower(email)) WHERE email_verified;
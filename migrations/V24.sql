@@ -0,0 +1,6 @@
+-- The client that started the login, only that client may complete it.
+ALTER TABLE oidc_logins
+    ADD COLUMN user_agent    TEXT,
+    ADD COLUMN ip_address    INET,
+    ADD COLUMN tls_version   TEXT,
+    ADD COLUMN alpn_protocol TEXT;
@@ -0,0 +1,20 @@
+-- The client details of an event are personal data, so they are scrubbed after the retention period
+-- (see AUDIT_RETENTION_DAYS). That is the only change the trail accepts, everything else is still kept.
+CREATE OR REPLACE FUNCTION reject_audit_event_change()
+    RETURNS TRIGGER AS
+$$
+BEGIN
+    IF TG_OP = 'UPDATE'
+        AND NEW.ip_address IS NULL
+        AND NEW.user_agent IS NULL
+        AND (NEW.id, NEW.actor_id, NEW.action, NEW.target_user_id, NEW.target_tweet_id, NEW.details, NEW.request_id, NEW.time_created)
+            IS NOT DISTINCT FROM
+            (OLD.id, OLD.actor_id, OLD.action, OLD.target_user_id, OLD.target_tweet_id, OLD.details, OLD.request_id, OLD.time_created) THEN
+        RETURN NEW;
+    END IF;
+
+    RAISE EXCEPTION 'audit_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE INDEX audit_events_client_index ON audit_events (time_created) WHERE ip_address IS NOT NULL OR user_agent IS NOT NULL;
@@ -0,0 +1,21 @@
+-- Both users are locked in the order of their ids before their counts are updated, otherwise A following B
+-- while B follows A locks the same rows in opposite order and deadlocks.
+CREATE OR REPLACE FUNCTION update_follow_counts()
+    RETURNS TRIGGER AS
+$$
+BEGIN
+    IF tg_op = 'INSERT' THEN
+        PERFORM FROM users WHERE id IN (new.follower_id, new.followee_id) ORDER BY id FOR NO KEY UPDATE;
+
+        UPDATE users SET following_count = following_count + 1 WHERE id = new.follower_id;
+        UPDATE users SET follower_count = follower_count + 1 WHERE id = new.followee_id;
+        RETURN new;
+    ELSE
+        PERFORM FROM users WHERE id IN (old.follower_id, old.followee_id) ORDER BY id FOR NO KEY UPDATE;
+
+        UPDATE users SET following_count = following_count - 1 WHERE id = old.follower_id;
+        UPDATE users SET follower_count = follower_count - 1 WHERE id = old.followee_id;
+        RETURN old;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,3 @@
+-- searches without words to rank by are ordered by the time alone, either of all tweets or of one author
+CREATE INDEX tweets_time_created_index ON tweets (time_created DESC, id DESC);
+CREATE INDEX tweets_user_id_time_created_index ON tweets (user_id, time_created DESC, id DESC);
@@ -0,0 +1,4 @@
+ALTER TABLE users
+    ADD COLUMN time_deactivated BIGINT;
+
+CREATE INDEX users_time_deactivated_index ON users (time_deactivated) WHERE time_deactivated IS NOT NULL;
@@ -0,0 +1,20 @@
+CREATE TABLE login_throttles
+(
+--  'username:<username>' or 'ip:<address>'
+    subject           TEXT    NOT NULL PRIMARY KEY,
+    failure_count     INTEGER NOT NULL DEFAULT 1,
+    time_last_failure BIGINT  NOT NULL DEFAULT extract(EPOCH FROM now()),
+    time_locked_until BIGINT  NOT NULL DEFAULT 0
+);
+
+CREATE TABLE security_events
+(
+    id           BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    user_id      BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    action       TEXT   NOT NULL,
+    ip_address   INET,
+    user_agent   TEXT,
+    time_created BIGINT NOT NULL DEFAULT extract(EPOCH FROM now())
+);
+
+CREATE INDEX security_events_user_id_index ON security_events (user_id, time_created);
@@ -0,0 +1,20 @@
+ALTER TABLE users
+    ADD COLUMN totp_secret    TEXT,
+    ADD COLUMN totp_enabled   BOOLEAN NOT NULL DEFAULT false,
+--  The time step of the last accepted code, so that every code can only be used once.
+    ADD COLUMN totp_last_step BIGINT;
+
+CREATE TABLE recovery_codes
+(
+    user_id   BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    code_hash TEXT   NOT NULL,
+    PRIMARY KEY (user_id, code_hash)
+);
+
+CREATE TABLE two_factor_challenges
+(
+    id           BIGINT  NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    user_id      BIGINT  NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    attempts     INTEGER NOT NULL DEFAULT 0,
+    time_created BIGINT  NOT NULL DEFAULT extract(EPOCH FROM now())
+);
@@ -0,0 +1,14 @@
+ALTER TABLE users
+    ADD COLUMN email TEXT;
+
+CREATE TABLE password_resets
+(
+    id           BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    user_id      BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+--  sha256 of the token, the token itself is only ever sent to the user.
+    token_hash   TEXT   NOT NULL UNIQUE,
+    time_created BIGINT NOT NULL DEFAULT extract(EPOCH FROM now()),
+    time_used    BIGINT
+);
+
+CREATE INDEX password_resets_user_id_index ON password_resets (user_id);
@@ -0,0 +1,17 @@
+ALTER TABLE users
+    ADD COLUMN email_verified BOOLEAN NOT NULL DEFAULT false;
+
+CREATE UNIQUE INDEX users_email_index ON users (lower(email));
+
+CREATE TABLE email_verifications
+(
+    id           BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    user_id      BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+--  The address being verified, so that a token can't verify an address set after it was sent.
+    email        TEXT   NOT NULL,
+--  sha256 of the token, the token itself is only ever sent to the user.
+    token_hash   TEXT   NOT NULL UNIQUE,
+    time_created BIGINT NOT NULL DEFAULT extract(EPOCH FROM now())
+);
+
+CREATE INDEX email_verifications_user_id_index ON email_verifications (user_id);
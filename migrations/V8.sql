@@ -0,0 +1,21 @@
+CREATE TABLE identities
+(
+--  An account at an OpenID Connect provider, the subject is only unique per issuer.
+    issuer       TEXT   NOT NULL,
+    subject      TEXT   NOT NULL,
+    user_id      BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    time_created BIGINT NOT NULL DEFAULT extract(EPOCH FROM now()),
+    PRIMARY KEY (issuer, subject)
+);
+
+CREATE INDEX identities_user_id_index ON identities (user_id);
+
+CREATE TABLE oidc_logins
+(
+    id            BIGINT NOT NULL PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+--  sha256 of the state parameter.
+    state_hash    TEXT   NOT NULL UNIQUE,
+    code_verifier TEXT   NOT NULL,
+    nonce         TEXT   NOT NULL,
+    time_created  BIGINT NOT NULL DEFAULT extract(EPOCH FROM now())
+);
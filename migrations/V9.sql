@@ -0,0 +1,45 @@
+-- Usernames are normalized (NFKC) and unique regardless of case from now on, so existing ones
+-- are normalized like new ones, and accounts that then only differ in case from an older one
+-- get renamed.
+ALTER TABLE users
+    DROP CONSTRAINT users_username_key;
+
+UPDATE users
+SET username = normalize(username, NFKC)
+WHERE username IS NOT NFKC NORMALIZED;
+
+DO
+$$
+    DECLARE
+        duplicate RECORD;
+        suffix    TEXT;
+        candidate TEXT;
+        attempt   INT;
+    BEGIN
+        FOR duplicate IN SELECT id, username
+                         FROM (SELECT id, username, row_number() OVER (PARTITION BY lower(username) ORDER BY id) AS n
+                               FROM users) AS duplicates
+                         WHERE n > 1
+                         ORDER BY id
+            LOOP
+                attempt := 0;
+
+                -- the suffix alone could match an existing name like alice_5, so it's extended until it doesn't
+                LOOP
+                    suffix := duplicate.id::TEXT || CASE WHEN attempt = 0 THEN '' ELSE '_' || attempt END;
+                    candidate := left(duplicate.username, 31 - length(suffix)) || '_' || suffix;
+
+                    EXIT WHEN NOT EXISTS (SELECT FROM users WHERE lower(username) = lower(candidate));
+
+                    attempt := attempt + 1;
+                END LOOP;
+
+                UPDATE users SET username = candidate WHERE id = duplicate.id;
+
+                -- the user has to learn the new name somehow
+                INSERT INTO security_events (user_id, action) VALUES (duplicate.id, 'username_changed');
+            END LOOP;
+    END
+$$;
+
+CREATE UNIQUE INDEX users_username_index ON users (lower(username));
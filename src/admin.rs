@@ -0,0 +1,113 @@
+use crate::audit;
+use crate::auth::Role;
+use crate::common::{Database, Request};
+
+/// Staff can only act on users below their own role, and never on themselves,
+/// so that the last admin can't lock everybody out.
+#[inline(always)]
+pub fn can_manage(actor_id: i64, actor_role: Role, target_id: i64, target_role: Role) -> bool {
+    actor_id != target_id && actor_role > target_role
+}
+
+/// Tweets can't lock anybody out, so admins can delete any of them, including their own and
+/// those of other admins. Moderators are held to `can_manage`.
+#[inline(always)]
+pub fn can_delete_tweet(
+    actor_id: i64,
+    actor_role: Role,
+    author_id: i64,
+    author_role: Role,
+) -> bool {
+    actor_role == Role::Admin || can_manage(actor_id, actor_role, author_id, author_role)
+}
+
+/// Writes an administrative action to the audit trail. Should be called within the
+/// transaction of the action itself, so that no action goes unrecorded.
+pub async fn record_action<'a>(
+    executor: impl sqlx::PgExecutor<'a>,
+    request: &Request,
+    actor_id: i64,
+    action: &str,
+    target_user_id: Option<i64>,
+    target_tweet_id: Option<i64>,
+    details: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let event = audit::Event {
+        action,
+        actor_id: Some(actor_id),
+        target_user_id,
+        target_tweet_id,
+        details,
+    };
+
+    audit::record(executor, event, request).await?;
+
+    log::info!(
+        "User {} did {} (user {:?}, tweet {:?})",
+        actor_id,
+        action,
+        target_user_id,
+        target_tweet_id
+    );
+
+    Ok(())
+}
+
+/// Makes the user named by `ADMIN_USERNAME` an admin, as long as there is no admin yet, so that
+/// the first admin doesn't have to be appointed in the database.
+pub async fn bootstrap(database: &Database) {
+    let username = match std::env::var("ADMIN_USERNAME") {
+        Ok(username) if !username.is_empty() => username,
+        _ => {
+            return;
+        }
+    };
+
+    let result = sqlx::query("UPDATE users SET role = 'admin' WHERE lower(username) = lower($1) AND NOT EXISTS (SELECT FROM users WHERE role = 'admin')")
+        .bind(&username)
+        .execute(database)
+        .await;
+
+    match result {
+        Ok(result) if result.rows_affected() > 0 => {
+            log::info!("Appointed {} as the first admin", username);
+        }
+        Ok(_) => {
+            log::info!(
+                "Did not appoint {} as admin, the user does not exist or there already is an admin",
+                username
+            );
+        }
+        Err(e) => {
+            log::warn!("Failed to appoint the first admin: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_can_manage() {
+        use crate::admin::can_manage;
+        use crate::auth::Role;
+
+        assert!(can_manage(1, Role::Admin, 2, Role::Moderator));
+        assert!(can_manage(1, Role::Moderator, 2, Role::User));
+        assert!(!can_manage(1, Role::Moderator, 2, Role::Moderator));
+        assert!(!can_manage(1, Role::Moderator, 2, Role::Admin));
+        assert!(!can_manage(1, Role::Admin, 1, Role::User));
+    }
+
+    #[test]
+    fn test_can_delete_tweet() {
+        use crate::admin::can_delete_tweet;
+        use crate::auth::Role;
+
+        assert!(can_delete_tweet(1, Role::Admin, 2, Role::Admin));
+        assert!(can_delete_tweet(1, Role::Admin, 1, Role::Admin));
+        assert!(can_delete_tweet(1, Role::Moderator, 2, Role::User));
+        assert!(!can_delete_tweet(1, Role::Moderator, 1, Role::Moderator));
+        assert!(!can_delete_tweet(1, Role::Moderator, 2, Role::Moderator));
+        assert!(!can_delete_tweet(1, Role::Moderator, 2, Role::Admin));
+    }
+}
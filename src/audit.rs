@@ -0,0 +1,71 @@
+use crate::common::*;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use once_cell::sync::Lazy;
+
+/// Something that happened, who did it and what it was done to.
+pub struct Event<'a> {
+    pub action: &'a str,
+    /// `None` if nobody was logged in, e.g. on failed logins.
+    pub actor_id: Option<i64>,
+    pub target_user_id: Option<i64>,
+    pub target_tweet_id: Option<i64>,
+    pub details: Option<&'a str>,
+}
+
+/// How long the IP address and the user agent of an event are kept, in seconds. The events
+/// themselves are kept forever.
+pub static RETENTION: Lazy<i64> =
+    Lazy::new(|| crate::env_param!("AUDIT_RETENTION_DAYS", 90i64).max(0) * 24 * 60 * 60);
+
+/// Identifies a request across the audit trail, the logs and the `X-Request-Id` response header.
+pub struct RequestId(pub String);
+
+#[inline(always)]
+pub fn generate_request_id() -> RequestId {
+    let mut id = [0u8; 12];
+    OsRng.fill_bytes(&mut id);
+    RequestId(base64::encode_config(id, base64::URL_SAFE_NO_PAD))
+}
+
+/// Appends an event to the audit trail, along with the client that caused it. Should be called
+/// within the transaction of the change itself, if there is one, so that nothing goes unrecorded.
+pub async fn record<'a>(
+    executor: impl sqlx::PgExecutor<'a>,
+    event: Event<'_>,
+    request: &Request,
+) -> std::result::Result<(), sqlx::Error> {
+    let client = client!(request);
+    let request_id = request.extensions().get::<RequestId>().map(|id| &id.0);
+
+    sqlx::query("INSERT INTO audit_events (actor_id, action, target_user_id, target_tweet_id, details, ip_address, user_agent, request_id) VALUES ($1, $2, $3, $4, $5, $6::inet, $7, $8)")
+        .bind(event.actor_id)
+        .bind(event.action)
+        .bind(event.target_user_id)
+        .bind(event.target_tweet_id)
+        .bind(event.details)
+        .bind(client.address.to_string())
+        .bind(user_agent!(request))
+        .bind(request_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Records something the user did to their own account, which they can later review.
+pub async fn record_event<'a>(
+    executor: impl sqlx::PgExecutor<'a>,
+    user_id: i64,
+    action: &str,
+    request: &Request,
+) -> std::result::Result<(), sqlx::Error> {
+    let event = Event {
+        action,
+        actor_id: Some(user_id),
+        target_user_id: Some(user_id),
+        target_tweet_id: None,
+        details: None,
+    };
+
+    record(executor, event, request).await
+}
@@ -1,25 +1,152 @@
+use crate::common::{Client, Database, Request};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
 use hmac::{Hmac, Mac};
-use sha2::Sha256;
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+use sqlx::Row;
+use std::net::IpAddr;
 
-static AUTH_SECRET: once_cell::sync::Lazy<String> =
-    once_cell::sync::Lazy::new(|| std::env::var("AUTH_SECRET").unwrap_or(String::from("secret")));
+static AUTH_SECRET: Lazy<String> =
+    Lazy::new(|| std::env::var("AUTH_SECRET").unwrap_or(String::from("secret")));
+
+/// What to do when a request does not come from the client the session was created by.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SessionBinding {
+    Off,
+    Warn,
+    Enforce,
+}
+
+static SESSION_BINDING: Lazy<SessionBinding> =
+    Lazy::new(|| match std::env::var("SESSION_BINDING").as_deref() {
+        Ok("off") => SessionBinding::Off,
+        Ok("enforce") => SessionBinding::Enforce,
+        _ => SessionBinding::Warn,
+    });
+
+// mobile clients hop between addresses all the time, so we only compare the network prefix
+static SESSION_BINDING_IPV4_PREFIX: Lazy<u8> =
+    Lazy::new(|| crate::env_param!("SESSION_BINDING_IPV4_PREFIX", 24));
+
+static SESSION_BINDING_IPV6_PREFIX: Lazy<u8> =
+    Lazy::new(|| crate::env_param!("SESSION_BINDING_IPV6_PREFIX", 64));
+
+/// Roles are ordered, every role can do everything the ones before it can.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    User,
+    Moderator,
+    Admin,
+}
+
+impl Role {
+    #[inline(always)]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Moderator => "moderator",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = ();
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        match val {
+            "user" => Ok(Self::User),
+            "moderator" => Ok(Self::Moderator),
+            "admin" => Ok(Self::Admin),
+            _ => Err(()),
+        }
+    }
+}
 
 #[macro_export]
 macro_rules! signature {
-    ($session_id:expr) => {{
+    ($($part:expr),+) => {{
         let mut mac = Hmac::<Sha256>::new_from_slice(AUTH_SECRET.as_bytes()).unwrap();
-        mac.update($session_id);
+        $(mac.update($part);)+
         mac.finalize().into_bytes()
     }};
 }
 
+// the domain keeps tokens of one kind from being accepted as tokens of another kind,
+// session tokens have an empty one, so that tokens issued before it existed stay valid
+const CHALLENGE_DOMAIN: &[u8] = b"challenge:";
+const PROOF_OF_WORK_DOMAIN: &[u8] = b"proof_of_work:";
+
 #[inline(always)]
 pub fn create_token(session_id: &[u8]) -> String {
+    sign_token(b"", session_id)
+}
+
+#[inline(always)]
+pub fn decode_token(token: &[u8]) -> Result<i64, ()> {
+    verify_token(b"", token)
+}
+
+/// A short-lived token proving that the password check of a two-factor login has passed.
+#[inline(always)]
+pub fn create_challenge_token(challenge_id: &[u8]) -> String {
+    sign_token(CHALLENGE_DOMAIN, challenge_id)
+}
+
+#[inline(always)]
+pub fn decode_challenge_token(token: &[u8]) -> Option<i64> {
+    verify_token(CHALLENGE_DOMAIN, token).ok()
+}
+
+/// Signs a proof-of-work challenge for the address it is handed out to, so that the server
+/// doesn't have to remember the challenges and they can't be solved on behalf of others.
+#[inline(always)]
+pub fn sign_proof_of_work(challenge: &[u8], address: IpAddr) -> String {
+    let signature = signature!(
+        PROOF_OF_WORK_DOMAIN,
+        address.to_string().as_bytes(),
+        challenge
+    );
+
+    base64::encode_config([challenge, &signature].concat(), base64::URL_SAFE_NO_PAD)
+}
+
+#[inline(always)]
+pub fn verify_proof_of_work(token: &str, address: IpAddr) -> Option<Vec<u8>> {
+    let mut challenge = base64::decode_config(token, base64::URL_SAFE_NO_PAD).ok()?;
+
+    if challenge.len() < 32 {
+        return None;
+    }
+
+    let signature = challenge.split_off(challenge.len() - 32);
+
+    let valid_signature = signature!(
+        PROOF_OF_WORK_DOMAIN,
+        address.to_string().as_bytes(),
+        &challenge
+    );
+
+    // compare every byte, so that the time taken doesn't tell how much of it was right
+    let difference = signature
+        .iter()
+        .zip(valid_signature.iter())
+        .fold(0, |difference, (a, b)| difference | (a ^ b));
+
+    if difference != 0 {
+        return None;
+    }
+
+    Some(challenge)
+}
+
+#[inline(always)]
+fn sign_token(domain: &[u8], session_id: &[u8]) -> String {
     // session_id + hmacsha256 signature, kind of like JWT but more efficient
     let mut token = [0u8; 40];
 
     unsafe {
-        let signature = signature!(session_id);
+        let signature = signature!(domain, session_id);
         std::ptr::copy_nonoverlapping(session_id.as_ptr(), token.as_mut_ptr(), 8);
         std::ptr::copy_nonoverlapping(signature.as_ptr(), token.as_mut_ptr().offset(8), 32);
     }
@@ -28,7 +155,7 @@ pub fn create_token(session_id: &[u8]) -> String {
 }
 
 #[inline(always)]
-pub fn decode_token(token: &[u8]) -> Result<i64, ()> {
+fn verify_token(domain: &[u8], token: &[u8]) -> Result<i64, ()> {
     let token = match base64::decode(token) {
         Ok(token) => token,
         Err(_) => {
@@ -42,7 +169,7 @@ pub fn decode_token(token: &[u8]) -> Result<i64, ()> {
 
     let (session_id, signature) = token.split_at(8);
 
-    let valid_signature = signature!(session_id);
+    let valid_signature = signature!(domain, session_id);
 
     for index in 0..32 {
         if signature[index] != valid_signature[index] {
@@ -57,6 +184,225 @@ pub fn decode_token(token: &[u8]) -> Result<i64, ()> {
     Ok(i64::from_le_bytes(buf))
 }
 
+/// Returns 256 random bits, encoded as 43 characters of unpadded base64url. Used for tokens
+/// that are looked up by their hash rather than signed, like the ones in mailed links.
+#[inline(always)]
+pub fn generate_random_token() -> String {
+    let mut token = [0u8; 32];
+    OsRng.fill_bytes(&mut token);
+    base64::encode_config(token, base64::URL_SAFE_NO_PAD)
+}
+
+/// Only the hash of a random token is stored, so that a leaked database can't be used to
+/// take over accounts. The tokens are random enough to not need a slow hash.
+#[inline(always)]
+pub fn hash_random_token(token: &str) -> String {
+    base64::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Creates a session for the user and returns its token.
+pub async fn create_session(
+    database: &Database,
+    user_id: i64,
+    request: &Request,
+) -> Result<String, sqlx::Error> {
+    // logging in restores a deactivated account
+    let result = sqlx::query(
+        "UPDATE users SET time_deactivated = NULL WHERE id = $1 AND time_deactivated IS NOT NULL",
+    )
+    .bind(user_id)
+    .execute(database)
+    .await?;
+
+    if result.rows_affected() != 0 {
+        crate::audit::record_event(database, user_id, "user_restored", request).await?;
+
+        log::info!("Restored user {}", user_id);
+    }
+
+    let client = crate::client!(request);
+
+    // remember who created the session, so that check_session can detect stolen tokens
+    let row = sqlx::query("INSERT INTO sessions (user_id, user_agent, ip_address, tls_version, alpn_protocol) VALUES ($1, $2, $3::inet, $4, $5) RETURNING id")
+        .bind(user_id)
+        .bind(crate::user_agent!(request))
+        .bind(client.address.to_string())
+        .bind(client.tls_version)
+        .bind(&client.alpn_protocol)
+        .fetch_one(database)
+        .await?;
+
+    let session_id = row.get_unchecked::<i64, _>(0);
+
+    let event = crate::audit::Event {
+        action: "session_created",
+        actor_id: Some(user_id),
+        target_user_id: Some(user_id),
+        target_tweet_id: None,
+        details: Some(&format!("session {}", session_id)),
+    };
+
+    crate::audit::record(database, event, request).await?;
+
+    Ok(create_token(&session_id.to_le_bytes()))
+}
+
+#[inline(always)]
+pub fn same_network(a: IpAddr, b: IpAddr, ipv4_prefix: u8, ipv6_prefix: u8) -> bool {
+    match (a, b) {
+        (IpAddr::V4(a), IpAddr::V4(b)) => {
+            let mask = u32::MAX
+                .checked_shl(32 - ipv4_prefix.min(32) as u32)
+                .unwrap_or(0);
+            u32::from(a) & mask == u32::from(b) & mask
+        }
+        (IpAddr::V6(a), IpAddr::V6(b)) => {
+            let mask = u128::MAX
+                .checked_shl(128 - ipv6_prefix.min(128) as u32)
+                .unwrap_or(0);
+            u128::from(a) & mask == u128::from(b) & mask
+        }
+        _ => false,
+    }
+}
+
+/// Compares the client of the request with the one remembered by `create_session` or
+/// `oidc_logins` and returns what doesn't match.
+pub fn binding_mismatches(
+    request: &Request,
+    user_agent: Option<&str>,
+    ip_address: Option<&str>,
+    tls_version: Option<&str>,
+    alpn_protocol: Option<&str>,
+) -> Vec<&'static str> {
+    let client = crate::client!(request);
+
+    let mut mismatches = Vec::new();
+
+    if user_agent != crate::user_agent!(request) {
+        mismatches.push("user agent");
+    }
+
+    let same_network = match ip_address {
+        Some(address) => match address.parse::<IpAddr>() {
+            Ok(address) => same_network(
+                address,
+                client.address,
+                *SESSION_BINDING_IPV4_PREFIX,
+                *SESSION_BINDING_IPV6_PREFIX,
+            ),
+            Err(_) => false,
+        },
+        None => false,
+    };
+
+    if !same_network {
+        mismatches.push("ip address");
+    }
+
+    if tls_version != client.tls_version {
+        mismatches.push("tls version");
+    }
+
+    if alpn_protocol != client.alpn_protocol.as_deref() {
+        mismatches.push("alpn protocol");
+    }
+
+    mismatches
+}
+
+/// Makes sure the session still exists and that the request comes from the same client
+/// the session was created by. Returns `false` if the session must be rejected.
+pub async fn check_session(
+    database: &Database,
+    session_id: i64,
+    request: &Request,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query("SELECT user_id, user_agent, host(ip_address), tls_version, alpn_protocol, time_suspended IS NOT NULL FROM sessions JOIN users ON users.id = sessions.user_id WHERE sessions.id = $1")
+        .bind(session_id)
+        .fetch_optional(database)
+        .await?;
+
+    let row = match row {
+        Some(row) => row,
+        None => {
+            return Ok(false);
+        }
+    };
+
+    // sessions are revoked on suspension, this only closes the gap to a concurrent login
+    if row.get_unchecked::<bool, _>(5) {
+        return Ok(false);
+    }
+
+    if *SESSION_BINDING == SessionBinding::Off {
+        return Ok(true);
+    }
+
+    let mismatches = binding_mismatches(
+        request,
+        row.get_unchecked(1),
+        row.get_unchecked(2),
+        row.get_unchecked(3),
+        row.get_unchecked(4),
+    );
+
+    if mismatches.is_empty() {
+        return Ok(true);
+    }
+
+    log::warn!(
+        "Suspicious request for session {} of user {} from {}: {} mismatch",
+        session_id,
+        row.get_unchecked::<i64, _>(0),
+        crate::client!(request).address,
+        mismatches.join(", ")
+    );
+
+    if *SESSION_BINDING == SessionBinding::Enforce {
+        sqlx::query("DELETE FROM sessions WHERE id = $1")
+            .bind(session_id)
+            .execute(database)
+            .await?;
+
+        return Ok(false);
+    }
+
+    Ok(true)
+}
+
+/// Returns the id and the role of the user the session belongs to.
+pub async fn get_role(
+    database: &Database,
+    session_id: i64,
+) -> Result<Option<(i64, Role)>, sqlx::Error> {
+    let row = sqlx::query(
+        "SELECT users.id, role FROM users JOIN sessions ON sessions.user_id = users.id WHERE sessions.id = $1",
+    )
+    .bind(session_id)
+    .fetch_optional(database)
+    .await?;
+
+    Ok(row.map(|row| {
+        (
+            row.get_unchecked(0),
+            row.get_unchecked::<&str, _>(1)
+                .parse()
+                .unwrap_or(Role::User),
+        )
+    }))
+}
+
+#[inline(always)]
+pub async fn is_suspended(database: &Database, user_id: i64) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query("SELECT time_suspended IS NOT NULL FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(database)
+        .await?;
+
+    Ok(matches!(row, Some(row) if row.get_unchecked::<bool, _>(0)))
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -71,4 +417,75 @@ mod tests {
         let session_id = crate::auth::decode_token(token.as_bytes());
         assert!(matches!(Err::<i64, ()>(()), session_id));
     }
+
+    #[test]
+    fn test_token_domains() {
+        let session_token = crate::auth::create_token(&1i64.to_le_bytes());
+        let challenge_token = crate::auth::create_challenge_token(&1i64.to_le_bytes());
+
+        assert_eq!(crate::auth::decode_token(session_token.as_bytes()), Ok(1));
+        assert_eq!(
+            crate::auth::decode_challenge_token(challenge_token.as_bytes()),
+            Some(1)
+        );
+        assert_eq!(
+            crate::auth::decode_token(challenge_token.as_bytes()),
+            Err(())
+        );
+        assert_eq!(
+            crate::auth::decode_challenge_token(session_token.as_bytes()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_generate_random_token() {
+        use crate::auth::{generate_random_token, hash_random_token};
+
+        let token = generate_random_token();
+
+        assert_eq!(token.len(), 43);
+        assert!(token
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_')));
+        assert_ne!(
+            hash_random_token(&token),
+            hash_random_token(&generate_random_token())
+        );
+    }
+
+    #[test]
+    fn test_roles() {
+        use crate::auth::Role;
+
+        assert!(Role::Admin > Role::Moderator);
+        assert!(Role::Moderator > Role::User);
+
+        for role in [Role::User, Role::Moderator, Role::Admin] {
+            assert_eq!(role.as_str().parse(), Ok(role));
+        }
+
+        assert_eq!("root".parse::<Role>(), Err(()));
+    }
+
+    #[test]
+    fn test_same_network() {
+        use crate::auth::same_network;
+
+        let a = "192.168.1.10".parse().unwrap();
+        let b = "192.168.1.200".parse().unwrap();
+        let c = "192.168.2.10".parse().unwrap();
+        let d = "2001:db8::1".parse().unwrap();
+        let e = "2001:db8::ffff:1".parse().unwrap();
+        let f = "2001:db9::1".parse().unwrap();
+
+        assert!(same_network(a, b, 24, 64));
+        assert!(!same_network(a, b, 32, 64));
+        assert!(!same_network(a, c, 24, 64));
+        assert!(same_network(a, c, 16, 64));
+        assert!(same_network(a, c, 0, 64));
+        assert!(same_network(d, e, 24, 64));
+        assert!(!same_network(d, f, 24, 64));
+        assert!(!same_network(a, d, 0, 0));
+    }
 }
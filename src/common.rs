@@ -61,6 +61,8 @@ impl Response<()> {
     error!(unauthorized, UNAUTHORIZED);
     error!(unsupported_media_type, UNSUPPORTED_MEDIA_TYPE);
     error!(method_not_allowed, METHOD_NOT_ALLOWED);
+    error!(bad_gateway, BAD_GATEWAY);
+    error!(forbidden, FORBIDDEN);
 }
 
 #[derive(serde::Deserialize)]
@@ -74,9 +76,61 @@ impl Credentials {
     pub fn is_invalid(&self) -> bool {
         self.username.len() < 3
             || self.username.len() > 32
-            || self.password.len() < 3
-            || self.password.len() > 128
+            || Self::is_invalid_password(&self.password)
     }
+
+    #[inline(always)]
+    pub fn is_invalid_password(password: &str) -> bool {
+        password.len() < 3 || password.len() > 128
+    }
+}
+
+#[derive(Clone, serde::Serialize)]
+pub struct User {
+    id: i64,
+    username: String,
+    display_name: Option<String>,
+    bio: Option<String>,
+    location: Option<String>,
+    website: Option<String>,
+    avatar: Option<String>,
+    follower_count: i32,
+    following_count: i32,
+    time_created: i64,
+}
+
+impl User {
+    /// The columns `from_row` expects, in that order.
+    pub const COLUMNS: &'static str = "users.id, users.username, users.display_name, users.bio, users.location, users.website, users.avatar, users.follower_count, users.following_count, users.time_created";
+
+    /// Reads the columns of `COLUMNS`, starting at `index`, so that users can be joined to other rows.
+    #[inline(always)]
+    pub fn from_row(row: &sqlx::postgres::PgRow, index: usize) -> Self {
+        Self {
+            id: row.get_unchecked(index),
+            username: row.get_unchecked(index + 1),
+            display_name: row.get_unchecked(index + 2),
+            bio: row.get_unchecked(index + 3),
+            location: row.get_unchecked(index + 4),
+            website: row.get_unchecked(index + 5),
+            avatar: row.get_unchecked(index + 6),
+            follower_count: row.get_unchecked(index + 7),
+            following_count: row.get_unchecked(index + 8),
+            time_created: row.get_unchecked(index + 9),
+        }
+    }
+}
+
+/// A mention, hashtag or URL in the text of a tweet. See `entities::extract` for the rules.
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Entity {
+    #[serde(rename = "type")]
+    kind: String,
+    start: i32,
+    end: i32,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user_id: Option<i64>,
 }
 
 #[derive(serde::Serialize)]
@@ -84,27 +138,221 @@ pub struct Tweet {
     id: i64,
     text: String,
     like_count: i32,
+    reply_count: i32,
+    retweet_count: i32,
+    quote_count: i32,
+    in_reply_to_tweet_id: Option<i64>,
+    conversation_id: i64,
+    quoted_tweet_id: Option<i64>,
     time_created: i64,
+    entities: Vec<Entity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<User>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    liked_by_me: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    replies: Option<Vec<Tweet>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    retweeted_by: Option<User>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_retweeted: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snippet: Option<String>,
 }
 
 impl Tweet {
+    /// The columns `from_row` expects, in that order.
+    pub const COLUMNS: &'static str = "tweets.id, tweets.text, tweets.like_count, tweets.reply_count, tweets.retweet_count, tweets.quote_count, tweets.in_reply_to_tweet_id, tweets.conversation_id, tweets.quoted_tweet_id, tweets.time_created, (SELECT coalesce(json_agg(json_build_object('type', tweet_entities.kind, 'start', tweet_entities.start_index, 'end', tweet_entities.end_index, 'text', tweet_entities.value, 'user_id', tweet_entities.user_id) ORDER BY tweet_entities.start_index), '[]')::TEXT FROM tweet_entities LEFT JOIN users AS mentioned ON mentioned.id = tweet_entities.user_id WHERE tweet_entities.tweet_id = tweets.id AND (mentioned.id IS NULL OR mentioned.time_deactivated IS NULL AND mentioned.time_suspended IS NULL))";
+
+    /// Reads the columns of `COLUMNS`, starting at `index`. The entities come as JSON, so that
+    /// they can be part of the same row.
+    #[inline(always)]
+    pub fn from_row(row: &sqlx::postgres::PgRow, index: usize) -> Self {
+        Self {
+            id: row.get_unchecked(index),
+            text: row.get_unchecked(index + 1),
+            like_count: row.get_unchecked(index + 2),
+            reply_count: row.get_unchecked(index + 3),
+            retweet_count: row.get_unchecked(index + 4),
+            quote_count: row.get_unchecked(index + 5),
+            in_reply_to_tweet_id: row.get_unchecked(index + 6),
+            conversation_id: row.get_unchecked(index + 7),
+            quoted_tweet_id: row.get_unchecked(index + 8),
+            time_created: row.get_unchecked(index + 9),
+            entities: serde_json::from_str(row.get_unchecked::<&str, _>(index + 10))
+                .unwrap_or_default(),
+            author: None,
+            liked_by_me: None,
+            replies: None,
+            retweeted_by: None,
+            time_retweeted: None,
+            snippet: None,
+        }
+    }
+
+    /// Tweets that are shown outside of the context of their author carry the author along.
+    #[inline(always)]
+    pub fn with_author(mut self, author: User) -> Self {
+        self.author = Some(author);
+        self
+    }
+
+    /// Only known if the tweet was looked up with an authorization token.
+    #[inline(always)]
+    pub fn with_liked_by_me(mut self, liked_by_me: bool) -> Self {
+        self.liked_by_me = Some(liked_by_me);
+        self
+    }
+
+    /// Only set in conversations, on the tweets whose replies were loaded.
     #[inline(always)]
-    pub fn new(id: i64, text: String, like_count: i32, time_created: i64) -> Self {
+    pub fn with_replies(mut self, replies: Vec<Tweet>) -> Self {
+        self.replies = Some(replies);
+        self
+    }
+
+    /// Retweets are shown as the tweet itself, among the tweets of whoever retweeted it.
+    #[inline(always)]
+    pub fn with_retweeted_by(mut self, user: User, time_retweeted: i64) -> Self {
+        self.retweeted_by = Some(user);
+        self.time_retweeted = Some(time_retweeted);
+        self
+    }
+
+    /// Only set in search results, the HTML-escaped text around the matches, which are wrapped in
+    /// `<mark>` tags.
+    #[inline(always)]
+    pub fn with_snippet(mut self, snippet: String) -> Self {
+        self.snippet = Some(snippet);
+        self
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct Invite {
+    id: i64,
+    code: String,
+    max_uses: i32,
+    use_count: i32,
+    time_expires: i64,
+    time_created: i64,
+}
+
+impl Invite {
+    #[inline(always)]
+    pub fn new(
+        id: i64,
+        code: String,
+        max_uses: i32,
+        use_count: i32,
+        time_expires: i64,
+        time_created: i64,
+    ) -> Self {
+        Self {
+            id,
+            code,
+            max_uses,
+            use_count,
+            time_expires,
+            time_created,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct SecurityEvent {
+    id: i64,
+    action: String,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    time_created: i64,
+}
+
+impl SecurityEvent {
+    #[inline(always)]
+    pub fn new(
+        id: i64,
+        action: String,
+        ip_address: Option<String>,
+        user_agent: Option<String>,
+        time_created: i64,
+    ) -> Self {
         Self {
             id,
-            text,
-            like_count,
+            action,
+            ip_address,
+            user_agent,
             time_created,
         }
     }
 }
 
+#[derive(serde::Serialize)]
+pub struct AuditEvent {
+    pub id: i64,
+    pub actor_id: Option<i64>,
+    pub action: String,
+    pub target_user_id: Option<i64>,
+    pub target_tweet_id: Option<i64>,
+    pub details: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub request_id: Option<String>,
+    pub time_created: i64,
+}
+
+/// Connection details shared by every request received over the same connection.
+pub struct Client {
+    pub address: std::net::IpAddr,
+    pub tls_version: Option<&'static str>,
+    pub alpn_protocol: Option<String>,
+}
+
+impl Client {
+    #[inline(always)]
+    pub fn new(
+        address: std::net::IpAddr,
+        connection: &rustls::ServerConnection,
+    ) -> std::sync::Arc<Self> {
+        // we are listening on [::], so ipv4 clients show up as ipv4-mapped ipv6 addresses
+        let address = match address {
+            std::net::IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+                Some(v4) => std::net::IpAddr::V4(v4),
+                None => address,
+            },
+            address => address,
+        };
+
+        std::sync::Arc::new(Self {
+            address,
+            tls_version: connection
+                .protocol_version()
+                .and_then(|version| version.as_str()),
+            alpn_protocol: connection
+                .alpn_protocol()
+                .map(|protocol| String::from_utf8_lossy(protocol).into_owned()),
+        })
+    }
+}
+
 pub type Result = std::result::Result<(StatusCode, Bytes), h2::Error>;
 pub type Request = http::Request<h2::RecvStream>;
 pub type Respond = h2::server::SendResponse<bytes::Bytes>;
 pub type Database = sqlx::Pool<sqlx::postgres::Postgres>;
 
-pub static ARGON2: Lazy<argon2::Argon2> = Lazy::new(|| argon2::Argon2::default());
+/// How long a deactivated account can be restored before it gets deleted for good.
+pub static ACCOUNT_GRACE_PERIOD: Lazy<i64> =
+    Lazy::new(|| env_param!("ACCOUNT_GRACE_PERIOD_DAYS", 30i64).max(0) * 24 * 60 * 60);
+
+#[macro_export]
+macro_rules! env_param {
+    ($name:literal, $default:expr) => {
+        std::env::var($name)
+            .ok()
+            .and_then(|val| val.parse().ok())
+            .unwrap_or($default)
+    };
+}
 
 #[macro_export]
 macro_rules! check_content_type {
@@ -158,8 +406,8 @@ macro_rules! unwrap_internal_error {
 
 #[macro_export]
 macro_rules! check_auth_token {
-    ($request:ident) => {{
-        match $request.headers().get(http::header::AUTHORIZATION) {
+    ($request:ident, $database:ident) => {{
+        let session_id = match $request.headers().get(http::header::AUTHORIZATION) {
             Some(token) => match auth::decode_token(token.as_bytes()) {
                 Ok(session_id) => session_id,
                 Err(_) => {
@@ -169,12 +417,176 @@ macro_rules! check_auth_token {
             None => {
                 return Ok(Response::unauthorized());
             }
+        };
+
+        if !unwrap_internal_error!(auth::check_session(&$database, session_id, $request).await) {
+            return Ok(Response::unauthorized());
+        }
+
+        session_id
+    }};
+}
+
+/// Like `check_auth_token!`, but for endpoints that can be used without an account as well.
+/// Evaluates to `None` if there is no token, an invalid one is still rejected.
+#[macro_export]
+macro_rules! optional_auth_token {
+    ($request:ident, $database:ident) => {{
+        if $request.headers().contains_key(http::header::AUTHORIZATION) {
+            Some(check_auth_token!($request, $database))
+        } else {
+            None
+        }
+    }};
+}
+
+/// Like `check_auth_token!`, but the user must also have at least the given role.
+/// Evaluates to the id and the role of the user.
+#[macro_export]
+macro_rules! check_role {
+    ($request:ident, $database:ident, $role:expr) => {{
+        let session_id = check_auth_token!($request, $database);
+
+        match unwrap_internal_error!(auth::get_role(&$database, session_id).await) {
+            Some((user_id, role)) if role >= $role => (user_id, role),
+            Some(_) => {
+                return Ok(Response::forbidden());
+            }
+            None => {
+                return Ok(Response::unauthorized());
+            }
         }
     }};
 }
 
+#[macro_export]
+macro_rules! client {
+    ($request:ident) => {
+        $request
+            .extensions()
+            .get::<std::sync::Arc<Client>>()
+            .unwrap()
+            .clone()
+    };
+}
+
+#[macro_export]
+macro_rules! user_agent {
+    ($request:ident) => {
+        $request
+            .headers()
+            .get(http::header::USER_AGENT)
+            .and_then(|val| val.to_str().ok())
+    };
+}
+
+#[macro_export]
+macro_rules! pagination {
+    ($request:ident) => {{
+        let mut limit = 50;
+        let mut offset = 0;
+
+        // why we still here?
+        if let Some(query) = $request.uri().query() {
+            for pair in query.split("&").take(2) {
+                if let Some((key, value)) = pair.split_once("=") {
+                    match key {
+                        "limit" => {
+                            if let Ok(value) = value.parse::<i32>() {
+                                if value < 0 || value > 50 {
+                                    return Ok(Response::bad_request());
+                                } else {
+                                    limit = value;
+                                }
+                            } else {
+                                return Ok(Response::bad_request());
+                            }
+                        }
+                        "offset" => {
+                            if let Ok(value) = value.parse::<i32>() {
+                                if value < 0 {
+                                    return Ok(Response::bad_request());
+                                } else {
+                                    offset = value;
+                                }
+                            } else {
+                                return Ok(Response::bad_request());
+                            }
+                        }
+                        _ => {
+                            return Ok(Response::bad_request());
+                        }
+                    }
+                } else {
+                    return Ok(Response::bad_request());
+                }
+            }
+        }
+
+        if limit < 0 || limit > 50 || offset < 0 {
+            return Ok(Response::bad_request());
+        }
+
+        (limit, offset)
+    }};
+}
+
+/// Like `pagination!`, but with a cursor from the previous page instead of an offset.
+#[macro_export]
+macro_rules! cursor_pagination {
+    ($request:ident) => {{
+        let mut limit = 50;
+        let mut cursor = cursor::Cursor::START;
+
+        if let Some(query) = $request.uri().query() {
+            for pair in query.split('&') {
+                let (key, value) = match pair.split_once('=') {
+                    Some(pair) => pair,
+                    None => {
+                        return Ok(Response::bad_request());
+                    }
+                };
+
+                match key {
+                    "limit" => match value.parse::<i32>() {
+                        Ok(value) if (0..=50).contains(&value) => limit = value,
+                        _ => {
+                            return Ok(Response::bad_request());
+                        }
+                    },
+                    "cursor" => match cursor::Cursor::decode(value) {
+                        Some(value) => cursor = value,
+                        None => {
+                            return Ok(Response::bad_request());
+                        }
+                    },
+                    _ => {
+                        return Ok(Response::bad_request());
+                    }
+                }
+            }
+        }
+
+        (limit, cursor)
+    }};
+}
+
 #[macro_export]
 macro_rules! parse_path_var {
+    // the index counts segments from the end, for paths like /admin/users/{id}/sessions
+    ($request:ident, $ty:ty, $index:expr) => {
+        match $request.uri().path().rsplit("/").nth($index) {
+            Some(val) => match val.parse::<$ty>() {
+                Ok(val) => val,
+                Err(_) => {
+                    return Ok(Response::bad_request());
+                }
+            },
+            None => {
+                unreachable!()
+            }
+        }
+    };
     ($request:ident, $ty:ty) => {
         match $request.uri().path().rsplit_once("/") {
             Some((_, val)) => match val.parse::<$ty>() {
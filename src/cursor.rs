@@ -0,0 +1,140 @@
+/// A position in a list that is ordered by time, newest first. The id breaks ties between
+/// entries of the same second. Unlike an offset, it stays put when new entries come in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cursor {
+    pub time: i64,
+    pub id: i64,
+}
+
+impl Cursor {
+    /// Comes before every entry, for the first page.
+    pub const START: Self = Self {
+        time: i64::MAX,
+        id: i64::MAX,
+    };
+
+    /// Clients are meant to pass cursors back as they are, so the format is opaque.
+    pub fn encode(&self) -> String {
+        base64::encode_config(self.to_bytes(), base64::URL_SAFE_NO_PAD)
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let bytes = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD).ok()?;
+
+        Some(Self::from_bytes(bytes.as_slice().try_into().ok()?))
+    }
+
+    /// Like `encode`, but the cursor only decodes with the same tag again. For lists that come in
+    /// several orders, where a position in one order means nothing in another.
+    pub fn encode_tagged(&self, tag: u8) -> String {
+        let mut bytes = [tag; 17];
+        bytes[1..].copy_from_slice(&self.to_bytes());
+
+        base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+    }
+
+    pub fn decode_tagged(cursor: &str, tag: u8) -> Option<Self> {
+        let bytes = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD).ok()?;
+
+        match bytes.split_first() {
+            Some((first, rest)) if *first == tag => Some(Self::from_bytes(rest.try_into().ok()?)),
+            _ => None,
+        }
+    }
+
+    fn to_bytes(self) -> [u8; 16] {
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&self.time.to_le_bytes());
+        bytes[8..].copy_from_slice(&self.id.to_le_bytes());
+
+        bytes
+    }
+
+    fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self {
+            time: i64::from_le_bytes(bytes[..8].try_into().unwrap()),
+            id: i64::from_le_bytes(bytes[8..].try_into().unwrap()),
+        }
+    }
+}
+
+/// Takes the page out of `rows`, which must have been fetched with a limit of one more than
+/// the page size, and returns the cursor of the next page, if there is one.
+#[inline(always)]
+pub fn next<T>(rows: &mut Vec<T>, limit: i32, position: impl Fn(&T) -> Cursor) -> Option<String> {
+    take_page(rows, limit, position).map(|cursor| cursor.encode())
+}
+
+/// Like `next`, but the cursor is tagged, see `Cursor::encode_tagged`.
+#[inline(always)]
+pub fn next_tagged<T>(
+    rows: &mut Vec<T>,
+    limit: i32,
+    tag: u8,
+    position: impl Fn(&T) -> Cursor,
+) -> Option<String> {
+    take_page(rows, limit, position).map(|cursor| cursor.encode_tagged(tag))
+}
+
+#[inline(always)]
+fn take_page<T>(rows: &mut Vec<T>, limit: i32, position: impl Fn(&T) -> Cursor) -> Option<Cursor> {
+    if rows.len() <= limit as usize {
+        return None;
+    }
+
+    rows.truncate(limit as usize);
+    rows.last().map(position)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cursor::*;
+
+    #[test]
+    fn test_encode_and_decode() {
+        let cursor = Cursor {
+            time: 1669185715,
+            id: 42,
+        };
+
+        assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(Cursor::decode(&Cursor::START.encode()), Some(Cursor::START));
+        assert_eq!(Cursor::decode(""), None);
+        assert_eq!(Cursor::decode("invalid!"), None);
+        assert_eq!(Cursor::decode(&cursor.encode()[1..]), None);
+    }
+
+    #[test]
+    fn test_encode_and_decode_tagged() {
+        let cursor = Cursor {
+            time: 1669185715,
+            id: 42,
+        };
+
+        assert_eq!(
+            Cursor::decode_tagged(&cursor.encode_tagged(1), 1),
+            Some(cursor)
+        );
+        assert_eq!(Cursor::decode_tagged(&cursor.encode_tagged(1), 0), None);
+        assert_eq!(Cursor::decode_tagged(&cursor.encode(), 0), None);
+        assert_eq!(Cursor::decode(&cursor.encode_tagged(0)), None);
+        assert_eq!(Cursor::decode_tagged("", 0), None);
+    }
+
+    #[test]
+    fn test_next() {
+        let position = |id: &i64| Cursor { time: 0, id: *id };
+
+        let mut rows = vec![5, 4, 3];
+        assert_eq!(next(&mut rows, 2, position), Some(position(&4).encode()));
+        assert_eq!(rows, vec![5, 4]);
+
+        let mut rows = vec![5, 4];
+        assert_eq!(next(&mut rows, 2, position), None);
+        assert_eq!(rows, vec![5, 4]);
+
+        let mut rows = vec![5];
+        assert_eq!(next(&mut rows, 0, position), None);
+        assert!(rows.is_empty());
+    }
+}
@@ -0,0 +1,89 @@
+use crate::auth;
+use crate::common::Database;
+use crate::mail::{self, Mail};
+use once_cell::sync::Lazy;
+
+/// Verification tokens expire after that many seconds.
+pub const VERIFICATION_LIFETIME: i64 = 24 * 60 * 60;
+
+/// Whether creating tweets requires a verified email address.
+pub static REQUIRE_VERIFIED_EMAIL: Lazy<bool> =
+    Lazy::new(|| crate::env_param!("REQUIRE_VERIFIED_EMAIL", false));
+
+/// Where the token leads to, `{token}` is replaced with the token itself.
+static EMAIL_VERIFICATION_URL: Lazy<String> = Lazy::new(|| {
+    std::env::var("EMAIL_VERIFICATION_URL").unwrap_or(String::from(
+        "https://localhost/email_verifications/{token}",
+    ))
+});
+
+/// A deliberately loose check, the only real validation is the verification mail.
+pub fn is_invalid(email: &str) -> bool {
+    let (local, domain) = match email.rsplit_once('@') {
+        Some(parts) => parts,
+        None => {
+            return true;
+        }
+    };
+
+    email.len() > 254
+        || local.is_empty()
+        || domain.is_empty()
+        || email
+            .chars()
+            .any(|char| char.is_whitespace() || char.is_control())
+}
+
+/// Replaces any pending verification of the user with one for the given address and mails it.
+pub async fn send_verification(
+    database: &Database,
+    user_id: i64,
+    username: &str,
+    email: &str,
+) -> Result<(), sqlx::Error> {
+    let token = auth::generate_random_token();
+
+    // only the latest address can be verified
+    sqlx::query("DELETE FROM email_verifications WHERE user_id = $1")
+        .bind(user_id)
+        .execute(database)
+        .await?;
+
+    sqlx::query("INSERT INTO email_verifications (user_id, email, token_hash) VALUES ($1, $2, $3)")
+        .bind(user_id)
+        .bind(email)
+        .bind(auth::hash_random_token(&token))
+        .execute(database)
+        .await?;
+
+    let url = EMAIL_VERIFICATION_URL.replace("{token}", &token);
+
+    mail::send(Mail {
+        to: email.to_string(),
+        subject: String::from("Verify your email address"),
+        body: format!(
+            "Hi {},\n\nplease follow the link below to verify your email address, it expires in {} hours:\n\n{}",
+            username,
+            VERIFICATION_LIFETIME / 60 / 60,
+            url
+        ),
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_is_invalid() {
+        use crate::email::is_invalid;
+
+        assert!(!is_invalid("hello@example.com"));
+        assert!(!is_invalid("hello+world@localhost"));
+        assert!(is_invalid("hello"));
+        assert!(is_invalid("@example.com"));
+        assert!(is_invalid("hello@"));
+        assert!(is_invalid("hello world@example.com"));
+        assert!(is_invalid(&format!("{}@example.com", "a".repeat(250))));
+    }
+}
@@ -0,0 +1,253 @@
+/// Longer tags aren't hashtags, just like longer names than `username::MAX_LENGTH` aren't mentions.
+const MAX_HASHTAG_LENGTH: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Mention,
+    Hashtag,
+    Url,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Mention => "mention",
+            Self::Hashtag => "hashtag",
+            Self::Url => "url",
+        }
+    }
+}
+
+/// A piece of the text of a tweet. The offsets count characters, not bytes, and the end is exclusive.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Extracted {
+    pub kind: Kind,
+    pub start: usize,
+    pub end: usize,
+    /// The username without the `@`, the tag without the `#` or the whole URL.
+    pub value: String,
+}
+
+#[inline(always)]
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn starts_with_ignore_case(text: &[char], prefix: &str) -> bool {
+    text.len() >= prefix.len()
+        && text
+            .iter()
+            .zip(prefix.chars())
+            .all(|(a, b)| a.eq_ignore_ascii_case(&b))
+}
+
+/// Finds the mentions, hashtags and URLs in the text. Nothing inside a URL counts as a mention or a
+/// hashtag, and none of them count if they are glued to the word in front of them, like in e-mail addresses.
+pub fn extract(text: &str) -> Vec<Extracted> {
+    let text = text.chars().collect::<Vec<char>>();
+    let mut entities = Vec::new();
+    let mut i = 0;
+
+    while i < text.len() {
+        if i > 0 && is_word(text[i - 1]) {
+            i += 1;
+            continue;
+        }
+
+        let rest = &text[i..];
+
+        let scheme = if starts_with_ignore_case(rest, "https://") {
+            8
+        } else if starts_with_ignore_case(rest, "http://") {
+            7
+        } else {
+            0
+        };
+
+        if scheme > 0 {
+            let mut end = i + rest.iter().take_while(|c| !c.is_whitespace()).count();
+
+            // punctuation after a link most likely belongs to the sentence, closing brackets only
+            // if they weren't opened within the link
+            while end > i + scheme {
+                let url = &text[i..end];
+                let last = url[url.len() - 1];
+
+                let unbalanced = last == ')'
+                    && url.iter().filter(|c| **c == '(').count()
+                        < url.iter().filter(|c| **c == ')').count();
+
+                if ".,;:!?'\"".contains(last) || unbalanced {
+                    end -= 1;
+                } else {
+                    break;
+                }
+            }
+
+            if end > i + scheme {
+                entities.push(Extracted {
+                    kind: Kind::Url,
+                    start: i,
+                    end,
+                    value: text[i..end].iter().collect(),
+                });
+
+                i = end;
+                continue;
+            }
+        }
+
+        let (kind, max_length) = match text[i] {
+            '@' => (Kind::Mention, crate::username::MAX_LENGTH),
+            '#' => (Kind::Hashtag, MAX_HASHTAG_LENGTH),
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+
+        let length = rest[1..].iter().take_while(|c| is_word(**c)).count();
+        let value = &rest[1..1 + length];
+
+        let valid = match kind {
+            Kind::Mention => value.iter().all(|c| c.is_ascii_alphanumeric() || *c == '_'),
+            // otherwise every number would be one
+            _ => !value.iter().all(|c| c.is_ascii_digit()),
+        };
+
+        if length > 0 && length <= max_length && valid {
+            entities.push(Extracted {
+                kind,
+                start: i,
+                end: i + 1 + length,
+                value: value.iter().collect(),
+            });
+        }
+
+        i += 1 + length;
+    }
+
+    entities
+}
+
+/// Replaces the entities of the tweet with the ones in its text. Mentions of unknown users, and
+/// of users that can't be seen, are left out.
+pub async fn store(
+    connection: &mut sqlx::PgConnection,
+    tweet_id: i64,
+    text: &str,
+) -> std::result::Result<(), sqlx::Error> {
+    let entities = extract(text);
+
+    sqlx::query("DELETE FROM tweet_entities WHERE tweet_id = $1")
+        .bind(tweet_id)
+        .execute(&mut *connection)
+        .await?;
+
+    sqlx::query("INSERT INTO tweet_entities (tweet_id, kind, start_index, end_index, value, user_id) SELECT $1, entities.kind, entities.start_index, entities.end_index, entities.value, users.id FROM UNNEST($2::TEXT[], $3::INTEGER[], $4::INTEGER[], $5::TEXT[]) AS entities (kind, start_index, end_index, value) LEFT JOIN users ON entities.kind = 'mention' AND lower(users.username) = lower(entities.value) AND users.time_deactivated IS NULL AND users.time_suspended IS NULL WHERE entities.kind <> 'mention' OR users.id IS NOT NULL")
+        .bind(tweet_id)
+        .bind(entities.iter().map(|entity| entity.kind.as_str()).collect::<Vec<&str>>())
+        .bind(entities.iter().map(|entity| entity.start as i32).collect::<Vec<i32>>())
+        .bind(entities.iter().map(|entity| entity.end as i32).collect::<Vec<i32>>())
+        .bind(entities.iter().map(|entity| entity.value.as_str()).collect::<Vec<&str>>())
+        .execute(&mut *connection)
+        .await?;
+
+    sqlx::query(
+        "UPDATE tweets SET entities_extracted = true WHERE id = $1 AND NOT entities_extracted",
+    )
+    .bind(tweet_id)
+    .execute(&mut *connection)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::entities::*;
+
+    fn extracted(text: &str) -> Vec<(Kind, usize, usize, String)> {
+        extract(text)
+            .into_iter()
+            .map(|entity| (entity.kind, entity.start, entity.end, entity.value))
+            .collect()
+    }
+
+    #[test]
+    fn test_mentions() {
+        assert_eq!(
+            extracted("@hello and @World_1!"),
+            vec![
+                (Kind::Mention, 0, 6, "hello".to_owned()),
+                (Kind::Mention, 11, 19, "World_1".to_owned()),
+            ]
+        );
+
+        // e-mail addresses, lone signs, names that are too long or can't be usernames
+        assert_eq!(extracted("mail@example.com"), vec![]);
+        assert_eq!(extracted("@ @@"), vec![]);
+        assert_eq!(extracted(&format!("@{}", "a".repeat(33))), vec![]);
+        assert_eq!(extracted("@héllo"), vec![]);
+    }
+
+    #[test]
+    fn test_hashtags() {
+        assert_eq!(
+            extracted("#rust, #größe #1 #2023_year"),
+            vec![
+                (Kind::Hashtag, 0, 5, "rust".to_owned()),
+                (Kind::Hashtag, 7, 13, "größe".to_owned()),
+                (Kind::Hashtag, 17, 27, "2023_year".to_owned()),
+            ]
+        );
+
+        assert_eq!(extracted("a#b"), vec![]);
+        assert_eq!(extracted(&format!("#{}", "a".repeat(101))), vec![]);
+    }
+
+    #[test]
+    fn test_urls() {
+        assert_eq!(
+            extracted("see https://example.com/a?b=c#d. or (HTTP://example.com)"),
+            vec![
+                (Kind::Url, 4, 31, "https://example.com/a?b=c#d".to_owned()),
+                (Kind::Url, 37, 55, "HTTP://example.com".to_owned()),
+            ]
+        );
+
+        assert_eq!(
+            extracted("https://en.wikipedia.org/wiki/Rust_(programming_language)"),
+            vec![(
+                Kind::Url,
+                0,
+                57,
+                "https://en.wikipedia.org/wiki/Rust_(programming_language)".to_owned()
+            )]
+        );
+
+        // nothing in a link is a mention or a hashtag
+        assert_eq!(
+            extracted("https://example.com/@hello#top @hello"),
+            vec![
+                (
+                    Kind::Url,
+                    0,
+                    30,
+                    "https://example.com/@hello#top".to_owned()
+                ),
+                (Kind::Mention, 31, 37, "hello".to_owned()),
+            ]
+        );
+
+        assert_eq!(extracted("https:// xhttps://example.com"), vec![]);
+    }
+
+    #[test]
+    fn test_offsets_count_characters() {
+        assert_eq!(
+            extracted("🦀 @hello"),
+            vec![(Kind::Mention, 2, 8, "hello".to_owned())]
+        );
+    }
+}
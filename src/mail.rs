@@ -0,0 +1,143 @@
+use once_cell::sync::Lazy;
+use std::future::Future;
+use std::pin::Pin;
+
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+pub type SendFuture<'a> = Pin<Box<dyn Future<Output = Result<(), Error>> + Send + 'a>>;
+
+pub trait Mailer: Send + Sync {
+    fn send(&self, mail: Mail) -> SendFuture<'_>;
+}
+
+static MAIL_FROM: Lazy<String> =
+    Lazy::new(|| std::env::var("MAIL_FROM").unwrap_or(String::from("noreply@localhost")));
+
+/// The mailer selected by the `MAILER` environment variable.
+pub static MAILER: Lazy<Box<dyn Mailer>> = Lazy::new(|| match std::env::var("MAILER").as_deref() {
+    Ok("smtp") => Box::new(SmtpMailer::from_env()),
+    Ok("file") => Box::new(FileMailer {
+        path: std::env::var("MAIL_FILE").unwrap_or(String::from("mail.log")),
+    }),
+    _ => Box::new(LogMailer),
+});
+
+/// Sends the mail in the background. Delivery can be slow, and waiting for it would also
+/// let clients tell whether a mail was sent at all.
+pub fn send(mail: Mail) {
+    tokio::spawn(async move {
+        let to = mail.to.clone();
+
+        if let Err(e) = MAILER.send(mail).await {
+            log::error!("Failed to send mail to {}: {}", to, e);
+        }
+    });
+}
+
+pub struct SmtpMailer {
+    transport: lettre::AsyncSmtpTransport<lettre::Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    pub fn from_env() -> Self {
+        use lettre::transport::smtp::authentication::Credentials;
+        use lettre::transport::smtp::client::{Tls, TlsParameters};
+
+        let host = std::env::var("SMTP_HOST").unwrap_or(String::from("localhost"));
+
+        let tls = match std::env::var("SMTP_TLS").as_deref() {
+            Ok("none") => Tls::None,
+            Ok("tls") => Tls::Wrapper(TlsParameters::new(host.clone()).unwrap()),
+            _ => Tls::Required(TlsParameters::new(host.clone()).unwrap()),
+        };
+
+        let port = crate::env_param!(
+            "SMTP_PORT",
+            match tls {
+                Tls::Wrapper(_) => 465,
+                _ => 587,
+            }
+        );
+
+        let mut builder =
+            lettre::AsyncSmtpTransport::<lettre::Tokio1Executor>::builder_dangerous(host)
+                .port(port)
+                .tls(tls);
+
+        if let (Ok(username), Ok(password)) = (
+            std::env::var("SMTP_USERNAME"),
+            std::env::var("SMTP_PASSWORD"),
+        ) {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+
+        Self {
+            transport: builder.build(),
+        }
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, mail: Mail) -> SendFuture<'_> {
+        use lettre::AsyncTransport;
+
+        Box::pin(async move {
+            let message = lettre::Message::builder()
+                .from(MAIL_FROM.parse()?)
+                .to(mail.to.parse()?)
+                .subject(mail.subject)
+                .body(mail.body)?;
+
+            self.transport.send(message).await?;
+
+            Ok(())
+        })
+    }
+}
+
+/// Appends every mail to a file, handy for development and tests.
+pub struct FileMailer {
+    pub path: String,
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, mail: Mail) -> SendFuture<'_> {
+        use tokio::io::AsyncWriteExt;
+
+        Box::pin(async move {
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .await?;
+
+            let mail = format!(
+                "From: {}\nTo: {}\nSubject: {}\n\n{}\n\n",
+                *MAIL_FROM, mail.to, mail.subject, mail.body
+            );
+
+            file.write_all(mail.as_bytes()).await?;
+
+            Ok(())
+        })
+    }
+}
+
+/// Only logs the mails, nothing is delivered.
+pub struct LogMailer;
+
+impl Mailer for LogMailer {
+    fn send(&self, mail: Mail) -> SendFuture<'_> {
+        Box::pin(async move {
+            log::info!("Mail to {} ({}):\n{}", mail.to, mail.subject, mail.body);
+
+            Ok(())
+        })
+    }
+}
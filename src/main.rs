@@ -1,5 +1,24 @@
+mod admin;
+mod audit;
 mod common;
+mod cursor;
+mod email;
+mod entities;
+mod mail;
+mod oidc;
+mod password;
+mod password_reset;
+mod profile;
+mod proof_of_work;
+mod registration;
 mod routes;
+mod search;
+mod tasks;
+mod throttle;
+mod timeline;
+mod tweet;
+mod two_factor;
+mod username;
 
 pub mod auth;
 
@@ -34,6 +53,21 @@ async fn main() {
         .await
         .unwrap();
 
+    admin::bootstrap(&database).await;
+
+    tokio::spawn(tasks::purge_deactivated_users(database.clone()));
+    tokio::spawn(tasks::purge_login_throttles(database.clone()));
+    tokio::spawn(tasks::purge_two_factor_challenges(database.clone()));
+    tokio::spawn(tasks::purge_password_resets(database.clone()));
+    tokio::spawn(tasks::purge_email_verifications(database.clone()));
+    tokio::spawn(tasks::purge_oidc_logins(database.clone()));
+    tokio::spawn(tasks::purge_spent_challenges(database.clone()));
+    tokio::spawn(tasks::scrub_audit_events(database.clone()));
+    tokio::spawn(tasks::extract_entities(database.clone()));
+
+    // hash it now rather than on the first login attempt of an unknown user
+    once_cell::sync::Lazy::force(&password::DUMMY_HASH);
+
     let server_address = std::env::var("SERVER_ADDRESS").unwrap_or(String::from("[::]:8443"));
 
     // setup a socket for accepting connections
@@ -44,10 +78,11 @@ async fn main() {
     loop {
         // try to accept a connection
         match listener.accept().await {
-            Ok((connection, _)) => {
+            Ok((connection, address)) => {
                 // spawn a task to asynchronously handle the connection
                 tokio::spawn(handle_connection(
                     connection,
+                    address.ip(),
                     tls_acceptor.clone(),
                     database.clone(),
                 ));
@@ -61,19 +96,24 @@ async fn main() {
 
 async fn handle_connection(
     connection: tokio::net::TcpStream,
+    address: std::net::IpAddr,
     tls_acceptor: tokio_rustls::TlsAcceptor,
     database: Database,
 ) {
     // try to perform a TLS handshake
     match tls_acceptor.accept(connection).await {
         Ok(connection) => {
+            let client = Client::new(address, connection.get_ref().1);
+
             // try to perform an HTTP/2 handshake
             match h2::server::handshake(connection).await {
                 Ok(mut connection) => {
                     // try to accept an HTTP/2 request
                     while let Some(result) = connection.accept().await {
                         match result {
-                            Ok((request, respond)) => {
+                            Ok((mut request, respond)) => {
+                                request.extensions_mut().insert(client.clone());
+
                                 // spawn a task to asynchronously handle the request
                                 tokio::spawn(handle_request(request, respond, database.clone()));
                             }
@@ -109,38 +149,243 @@ async fn handle_request(mut request: Request, mut respond: Respond, database: Da
         };
     }
 
+    let request_id = audit::generate_request_id();
+    let header = http::HeaderValue::from_str(&request_id.0).unwrap();
+
+    request.extensions_mut().insert(request_id);
+
     let result = match request.uri().path() {
         "/users" => match *request.method() {
             http::Method::POST => call!(routes::users::post),
             _ => Ok(Response::method_not_allowed()),
         },
+        "/challenges" => match *request.method() {
+            http::Method::GET => call!(routes::challenges::get),
+            _ => Ok(Response::method_not_allowed()),
+        },
+        "/tweets" => match *request.method() {
+            http::Method::GET => call!(routes::tweets::get),
+            _ => Ok(Response::method_not_allowed()),
+        },
+        "/password_resets" => match *request.method() {
+            http::Method::POST => call!(routes::password_resets::post),
+            _ => Ok(Response::method_not_allowed()),
+        },
+        "/admin/audit_events" => match *request.method() {
+            http::Method::GET => call!(routes::admin::audit_events::get),
+            _ => Ok(Response::method_not_allowed()),
+        },
+        "/admin/users" => match *request.method() {
+            http::Method::GET => call!(routes::admin::users::get),
+            _ => Ok(Response::method_not_allowed()),
+        },
+        "/users/@me" => match *request.method() {
+            http::Method::PATCH => call!(routes::users::patch),
+            http::Method::DELETE => call!(routes::users::delete),
+            _ => Ok(Response::method_not_allowed()),
+        },
         "/users/@me/sessions" => match *request.method() {
             http::Method::POST => call!(routes::users::sessions::post),
             _ => Ok(Response::method_not_allowed()),
         },
+        "/users/@me/sessions/2fa" => match *request.method() {
+            http::Method::POST => call!(routes::users::sessions::two_factor::post),
+            _ => Ok(Response::method_not_allowed()),
+        },
+        "/users/@me/sessions/oidc" => match *request.method() {
+            http::Method::POST => call!(routes::users::sessions::oidc::post),
+            _ => Ok(Response::method_not_allowed()),
+        },
+        "/users/@me/sessions/oidc/callback" => match *request.method() {
+            http::Method::POST => call!(routes::users::sessions::oidc::callback::post),
+            _ => Ok(Response::method_not_allowed()),
+        },
+        "/users/@me/2fa" => match *request.method() {
+            http::Method::POST => call!(routes::users::two_factor::post),
+            http::Method::DELETE => call!(routes::users::two_factor::delete),
+            _ => Ok(Response::method_not_allowed()),
+        },
+        "/users/@me/2fa/confirm" => match *request.method() {
+            http::Method::POST => call!(routes::users::two_factor::confirm::post),
+            _ => Ok(Response::method_not_allowed()),
+        },
+        "/users/@me/email" => match *request.method() {
+            http::Method::PATCH => call!(routes::users::email::patch),
+            _ => Ok(Response::method_not_allowed()),
+        },
+        "/users/@me/invites" => match *request.method() {
+            http::Method::POST => call!(routes::users::invites::post),
+            http::Method::GET => call!(routes::users::invites::get),
+            _ => Ok(Response::method_not_allowed()),
+        },
+        "/users/@me/password" => match *request.method() {
+            http::Method::PATCH => call!(routes::users::password::patch),
+            _ => Ok(Response::method_not_allowed()),
+        },
+        "/users/@me/username" => match *request.method() {
+            http::Method::PATCH => call!(routes::users::username::patch),
+            _ => Ok(Response::method_not_allowed()),
+        },
+        "/users/@me/security_events" => match *request.method() {
+            http::Method::GET => call!(routes::users::security_events::get),
+            _ => Ok(Response::method_not_allowed()),
+        },
         "/users/@me/tweets" => match *request.method() {
             http::Method::POST => call!(routes::users::tweets::post),
             http::Method::GET => call!(routes::users::tweets::get),
             _ => Ok(Response::method_not_allowed()),
         },
+        "/users/@me/timeline" => match *request.method() {
+            http::Method::GET => call!(routes::users::timeline::get),
+            _ => Ok(Response::method_not_allowed()),
+        },
+        "/users/@me/following" => match *request.method() {
+            http::Method::POST => call!(routes::users::following::post),
+            _ => Ok(Response::method_not_allowed()),
+        },
         "/users/@me/liked_tweets" => match *request.method() {
             http::Method::POST => call!(routes::users::liked_tweets::post),
+            http::Method::GET => call!(routes::users::liked_tweets::get),
             http::Method::DELETE => call!(routes::users::liked_tweets::delete),
             _ => Ok(Response::method_not_allowed()),
         },
+        "/users/@me/retweets" => match *request.method() {
+            http::Method::POST => call!(routes::users::retweets::post),
+            http::Method::DELETE => call!(routes::users::retweets::delete),
+            _ => Ok(Response::method_not_allowed()),
+        },
+        "/search/tweets" => match *request.method() {
+            http::Method::GET => call!(routes::search::tweets::get),
+            _ => Ok(Response::method_not_allowed()),
+        },
         path => {
             use once_cell::sync::Lazy;
             use regex::Regex;
 
+            static TWEET_REGEX: Lazy<Regex> =
+                Lazy::new(|| Regex::new("^/tweets/[0-9]{1,16}$").unwrap());
+
+            static TWEET_LIST_REGEX: Lazy<Regex> = Lazy::new(|| {
+                Regex::new("^/tweets/[0-9]{1,16}/(liking_users|conversation)$").unwrap()
+            });
+
+            static USER_REGEX: Lazy<Regex> =
+                Lazy::new(|| Regex::new("^/users/[0-9]{1,16}$").unwrap());
+
+            static USER_LIST_REGEX: Lazy<Regex> = Lazy::new(|| {
+                Regex::new("^/users/[0-9]{1,16}/(tweets|liked_tweets|followers|following)$")
+                    .unwrap()
+            });
+
+            static USERNAME_REGEX: Lazy<Regex> =
+                Lazy::new(|| Regex::new("^/users/by/username/[^/]+$").unwrap());
+
             static REGEX: Lazy<Regex> =
                 Lazy::new(|| Regex::new("^/users/@me/tweets/[0-9]{1,16}$").unwrap());
 
-            if REGEX.is_match(path) {
+            static FOLLOWING_REGEX: Lazy<Regex> =
+                Lazy::new(|| Regex::new("^/users/@me/following/[0-9]{1,16}$").unwrap());
+
+            static INVITE_REGEX: Lazy<Regex> =
+                Lazy::new(|| Regex::new("^/users/@me/invites/[0-9]{1,16}$").unwrap());
+
+            static PASSWORD_RESET_REGEX: Lazy<Regex> =
+                Lazy::new(|| Regex::new("^/password_resets/[A-Za-z0-9_-]{43}$").unwrap());
+
+            static EMAIL_VERIFICATION_REGEX: Lazy<Regex> =
+                Lazy::new(|| Regex::new("^/email_verifications/[A-Za-z0-9_-]{43}$").unwrap());
+
+            static ADMIN_USER_REGEX: Lazy<Regex> = Lazy::new(|| {
+                Regex::new("^/admin/users/[0-9]{1,16}/(suspension|sessions|role|invite_quota)$")
+                    .unwrap()
+            });
+
+            static ADMIN_TWEET_REGEX: Lazy<Regex> =
+                Lazy::new(|| Regex::new("^/admin/tweets/[0-9]{1,16}$").unwrap());
+
+            if TWEET_REGEX.is_match(path) {
+                match *request.method() {
+                    http::Method::GET => call!(routes::tweets::get),
+                    _ => Ok(Response::method_not_allowed()),
+                }
+            } else if let Some(captures) = TWEET_LIST_REGEX.captures(path) {
+                match (&captures[1], request.method().clone()) {
+                    ("liking_users", http::Method::GET) => {
+                        call!(routes::tweets::liking_users::get)
+                    }
+                    ("conversation", http::Method::GET) => {
+                        call!(routes::tweets::conversation::get)
+                    }
+                    _ => Ok(Response::method_not_allowed()),
+                }
+            } else if USER_REGEX.is_match(path) {
+                match *request.method() {
+                    http::Method::GET => call!(routes::users::get),
+                    _ => Ok(Response::method_not_allowed()),
+                }
+            } else if let Some(captures) = USER_LIST_REGEX.captures(path) {
+                match (&captures[1], request.method().clone()) {
+                    ("tweets", http::Method::GET) => call!(routes::users::tweets::get),
+                    ("liked_tweets", http::Method::GET) => {
+                        call!(routes::users::liked_tweets::get)
+                    }
+                    ("followers", http::Method::GET) => call!(routes::users::followers::get),
+                    ("following", http::Method::GET) => call!(routes::users::following::get),
+                    _ => Ok(Response::method_not_allowed()),
+                }
+            } else if USERNAME_REGEX.is_match(path) {
+                match *request.method() {
+                    http::Method::GET => call!(routes::users::by::username::get),
+                    _ => Ok(Response::method_not_allowed()),
+                }
+            } else if REGEX.is_match(path) {
                 match *request.method() {
                     http::Method::PATCH => call!(routes::users::tweets::patch),
                     http::Method::DELETE => call!(routes::users::tweets::delete),
                     _ => Ok(Response::method_not_allowed()),
                 }
+            } else if FOLLOWING_REGEX.is_match(path) {
+                match *request.method() {
+                    http::Method::DELETE => call!(routes::users::following::delete),
+                    _ => Ok(Response::method_not_allowed()),
+                }
+            } else if INVITE_REGEX.is_match(path) {
+                match *request.method() {
+                    http::Method::DELETE => call!(routes::users::invites::delete),
+                    _ => Ok(Response::method_not_allowed()),
+                }
+            } else if PASSWORD_RESET_REGEX.is_match(path) {
+                match *request.method() {
+                    http::Method::POST => call!(routes::password_resets::token::post),
+                    _ => Ok(Response::method_not_allowed()),
+                }
+            } else if EMAIL_VERIFICATION_REGEX.is_match(path) {
+                match *request.method() {
+                    http::Method::POST => call!(routes::email_verifications::token::post),
+                    _ => Ok(Response::method_not_allowed()),
+                }
+            } else if let Some(captures) = ADMIN_USER_REGEX.captures(path) {
+                match (&captures[1], request.method().clone()) {
+                    ("suspension", http::Method::POST) => {
+                        call!(routes::admin::users::suspension::post)
+                    }
+                    ("suspension", http::Method::DELETE) => {
+                        call!(routes::admin::users::suspension::delete)
+                    }
+                    ("sessions", http::Method::DELETE) => {
+                        call!(routes::admin::users::sessions::delete)
+                    }
+                    ("role", http::Method::PUT) => call!(routes::admin::users::role::put),
+                    ("invite_quota", http::Method::PUT) => {
+                        call!(routes::admin::users::invite_quota::put)
+                    }
+                    _ => Ok(Response::method_not_allowed()),
+                }
+            } else if ADMIN_TWEET_REGEX.is_match(path) {
+                match *request.method() {
+                    http::Method::DELETE => call!(routes::admin::tweets::delete),
+                    _ => Ok(Response::method_not_allowed()),
+                }
             } else {
                 Ok(Response::not_found())
             }
@@ -152,6 +397,7 @@ async fn handle_request(mut request: Request, mut respond: Respond, database: Da
             let response = http::Response::builder()
                 .status(code)
                 .header(http::header::CONTENT_TYPE, "application/json")
+                .header("x-request-id", header)
                 .body(())
                 .unwrap();
 
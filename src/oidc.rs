@@ -0,0 +1,441 @@
+use crate::common::Database;
+use once_cell::sync::Lazy;
+use sha2::Digest;
+use sqlx::Row;
+
+/// Logins must be completed within that many seconds after they were started.
+pub const LOGIN_LIFETIME: i64 = 10 * 60;
+
+pub struct Config {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: String,
+    /// Whether the provider may be reached over plain HTTP, which is only meant for tests.
+    pub insecure: bool,
+}
+
+/// `None` unless `OIDC_ISSUER` is set, which disables OpenID Connect logins.
+pub static CONFIG: Lazy<Option<Config>> = Lazy::new(|| {
+    let issuer = std::env::var("OIDC_ISSUER").ok()?;
+    let insecure = crate::env_param!("OIDC_INSECURE", false);
+
+    // ID tokens are trusted because they come from the token endpoint over TLS
+    if !insecure && !issuer.starts_with("https://") {
+        panic!("OIDC_ISSUER must be an https URL");
+    }
+
+    Some(Config {
+        // the issuer identifier is compared character by character, but people like to
+        // copy it with a trailing slash
+        issuer: issuer.trim_end_matches('/').to_string(),
+        client_id: std::env::var("OIDC_CLIENT_ID").expect("OIDC_CLIENT_ID is required"),
+        client_secret: std::env::var("OIDC_CLIENT_SECRET").ok(),
+        redirect_uri: std::env::var("OIDC_REDIRECT_URI").expect("OIDC_REDIRECT_URI is required"),
+        scopes: std::env::var("OIDC_SCOPES").unwrap_or(String::from("openid profile email")),
+        insecure,
+    })
+});
+
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        // neither the endpoints from the metadata nor redirects may leave TLS
+        .https_only(!matches!(&*CONFIG, Some(config) if config.insecure))
+        .build()
+        .unwrap()
+});
+
+/// https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderMetadata
+#[derive(serde::Deserialize)]
+struct Metadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+// fetched on the first login rather than on startup, so that the app starts even if the
+// provider is down
+static METADATA: Lazy<tokio::sync::OnceCell<Metadata>> = Lazy::new(tokio::sync::OnceCell::new);
+
+#[derive(Debug)]
+pub enum Error {
+    Request(reqwest::Error),
+    Provider(String),
+    IdToken(&'static str),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Request(e) => write!(f, "Failed to reach the identity provider: {}", e),
+            Self::Provider(e) => write!(f, "The identity provider refused: {}", e),
+            Self::IdToken(e) => write!(f, "Invalid ID token: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Request(e)
+    }
+}
+
+async fn metadata(config: &Config) -> Result<&'static Metadata, Error> {
+    METADATA
+        .get_or_try_init(|| async {
+            let url = format!("{}/.well-known/openid-configuration", config.issuer);
+
+            let metadata = CLIENT
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Metadata>()
+                .await?;
+
+            // https://openid.net/specs/openid-connect-discovery-1_0.html#ProviderConfigurationValidation
+            if metadata.issuer.trim_end_matches('/') != config.issuer {
+                return Err(Error::Provider(format!(
+                    "issuer mismatch: {}",
+                    metadata.issuer
+                )));
+            }
+
+            Ok(metadata)
+        })
+        .await
+}
+
+/// https://www.rfc-editor.org/rfc/rfc7636#section-4.2
+#[inline(always)]
+pub fn code_challenge(code_verifier: &str) -> String {
+    base64::encode_config(
+        sha2::Sha256::digest(code_verifier.as_bytes()),
+        base64::URL_SAFE_NO_PAD,
+    )
+}
+
+/// Returns where the user has to be sent to log in at the provider.
+pub async fn authorization_url(
+    config: &Config,
+    state: &str,
+    nonce: &str,
+    code_verifier: &str,
+) -> Result<String, Error> {
+    let metadata = metadata(config).await?;
+
+    let url = reqwest::Url::parse_with_params(
+        &metadata.authorization_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", &config.client_id),
+            ("redirect_uri", &config.redirect_uri),
+            ("scope", &config.scopes),
+            ("state", state),
+            ("nonce", nonce),
+            ("code_challenge", &code_challenge(code_verifier)),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .map_err(|e| Error::Provider(e.to_string()))?;
+
+    Ok(url.into())
+}
+
+/// Exchanges the authorization code for an ID token.
+pub async fn exchange_code(
+    config: &Config,
+    code: &str,
+    code_verifier: &str,
+) -> Result<String, Error> {
+    #[derive(serde::Deserialize)]
+    struct TokenResponse {
+        id_token: Option<String>,
+        error: Option<String>,
+    }
+
+    let metadata = metadata(config).await?;
+
+    let mut request = CLIENT.post(&metadata.token_endpoint).form(&[
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", &config.redirect_uri),
+        ("client_id", &config.client_id),
+        ("code_verifier", code_verifier),
+    ]);
+
+    // public clients only have PKCE
+    if let Some(client_secret) = &config.client_secret {
+        request = request.basic_auth(&config.client_id, Some(client_secret));
+    }
+
+    let response = request.send().await?.json::<TokenResponse>().await?;
+
+    match (response.id_token, response.error) {
+        (Some(id_token), None) => Ok(id_token),
+        (_, error) => Err(Error::Provider(
+            error.unwrap_or(String::from("no id_token")),
+        )),
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(serde::Deserialize)]
+pub struct Claims {
+    iss: String,
+    pub sub: String,
+    aud: Audience,
+    azp: Option<String>,
+    exp: i64,
+    nonce: Option<String>,
+    pub preferred_username: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+}
+
+/// Decodes the ID token and validates its claims.
+///
+/// The signature is not checked: the token comes straight from the token endpoint over TLS,
+/// in which case the TLS server validation may be used instead. Hence the provider is only
+/// reached over https, unless `OIDC_INSECURE` is set for testing.
+/// https://openid.net/specs/openid-connect-core-1_0.html#IDTokenValidation
+pub fn decode_id_token(
+    config: &Config,
+    id_token: &str,
+    nonce: &str,
+    now: i64,
+) -> Result<Claims, Error> {
+    let payload = match id_token.split('.').nth(1) {
+        Some(payload) => payload,
+        None => {
+            return Err(Error::IdToken("malformed"));
+        }
+    };
+
+    let payload = base64::decode_config(payload, base64::URL_SAFE_NO_PAD)
+        .map_err(|_| Error::IdToken("malformed"))?;
+
+    let claims =
+        serde_json::from_slice::<Claims>(&payload).map_err(|_| Error::IdToken("malformed"))?;
+
+    if claims.iss.trim_end_matches('/') != config.issuer {
+        return Err(Error::IdToken("wrong issuer"));
+    }
+
+    let audience_matches = match &claims.aud {
+        Audience::One(aud) => *aud == config.client_id,
+        Audience::Many(aud) => {
+            aud.contains(&config.client_id)
+                && claims
+                    .azp
+                    .as_ref()
+                    .map_or(aud.len() == 1, |azp| *azp == config.client_id)
+        }
+    };
+
+    if !audience_matches {
+        return Err(Error::IdToken("wrong audience"));
+    }
+
+    if claims.exp <= now {
+        return Err(Error::IdToken("expired"));
+    }
+
+    // the nonce ties the token to the login it was requested for, so that it can't be replayed
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(Error::IdToken("wrong nonce"));
+    }
+
+    Ok(claims)
+}
+
+/// Returns the user linked to the identity, creating one on the first login.
+/// Returns `None` if the linked account is past its grace period, or if there is none and
+/// registration isn't open, since there is no way to pass an invite along.
+pub async fn find_or_create_user(
+    database: &Database,
+    config: &Config,
+    claims: &Claims,
+) -> Result<Option<i64>, sqlx::Error> {
+    loop {
+        let row = sqlx::query("SELECT users.id, time_deactivated <= extract(EPOCH FROM now()) - $3 FROM identities JOIN users ON users.id = identities.user_id WHERE issuer = $1 AND subject = $2")
+            .bind(&config.issuer)
+            .bind(&claims.sub)
+            .bind(*crate::common::ACCOUNT_GRACE_PERIOD)
+            .fetch_optional(database)
+            .await?;
+
+        if let Some(row) = row {
+            if row.get_unchecked::<Option<bool>, _>(1) == Some(true) {
+                return Ok(None);
+            }
+
+            return Ok(Some(row.get_unchecked(0)));
+        }
+
+        if *crate::registration::MODE != crate::registration::Mode::Open {
+            return Ok(None);
+        }
+
+        let mut transaction = database.begin().await?;
+
+        // only addresses the provider has verified are taken over, and only if they are free
+        let email = match (&claims.email, claims.email_verified) {
+            (Some(email), Some(true)) if !crate::email::is_invalid(email) => {
+                let row =
+                    sqlx::query("SELECT EXISTS (SELECT FROM users WHERE lower(email) = lower($1) AND email_verified)")
+                        .bind(email)
+                        .fetch_one(&mut transaction)
+                        .await?;
+
+                (!row.get_unchecked::<bool, _>(0)).then_some(email)
+            }
+            _ => None,
+        };
+
+        let mut usernames = Vec::new();
+
+        if let Some(username) = &claims.preferred_username {
+            let username = crate::username::normalize(username);
+
+            if !crate::username::is_invalid(&username)
+                && !crate::username::is_reserved(&username)
+                && !crate::username::is_held(&mut transaction, &username, None).await?
+            {
+                usernames.push(username);
+            }
+        }
+
+        for _ in 0..3 {
+            usernames.push(crate::username::generate());
+        }
+
+        let mut user_id = None;
+
+        for username in usernames {
+            let row = sqlx::query("INSERT INTO users (username, email, email_verified) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING RETURNING id")
+                .bind(&username)
+                .bind(email)
+                .bind(email.is_some())
+                .fetch_optional(&mut transaction)
+                .await?;
+
+            if let Some(row) = row {
+                user_id = Some(row.get_unchecked::<i64, _>(0));
+                break;
+            }
+        }
+
+        let user_id = match user_id {
+            Some(user_id) => user_id,
+            None => {
+                return Err(sqlx::Error::Protocol(String::from(
+                    "Failed to find a free username",
+                )));
+            }
+        };
+
+        let result = sqlx::query("INSERT INTO identities (issuer, subject, user_id) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+            .bind(&config.issuer)
+            .bind(&claims.sub)
+            .bind(user_id)
+            .execute(&mut transaction)
+            .await?;
+
+        // a concurrent first login got there first, the transaction rolls back on drop
+        if result.rows_affected() == 0 {
+            continue;
+        }
+
+        transaction.commit().await?;
+
+        log::info!(
+            "Created user {} for {} of {}",
+            user_id,
+            claims.sub,
+            config.issuer
+        );
+
+        return Ok(Some(user_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::oidc::*;
+
+    fn config() -> Config {
+        Config {
+            issuer: String::from("https://idp.example.com"),
+            client_id: String::from("client"),
+            client_secret: None,
+            redirect_uri: String::from("https://localhost/callback"),
+            scopes: String::from("openid"),
+            insecure: false,
+        }
+    }
+
+    fn id_token(claims: serde_json::Value) -> String {
+        format!(
+            "e30.{}.c2ln",
+            base64::encode_config(claims.to_string(), base64::URL_SAFE_NO_PAD)
+        )
+    }
+
+    #[test]
+    fn test_code_challenge() {
+        // BASE64URL(SHA256(verifier)), cross-checked with openssl
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mJ92K9ZIxrNkQp2Ozl9uz4Nnj6fZlE"),
+            "Vv49LxeE9a-Oy6o-HL1UaISbjnD7oOGj9ex2Tf3YKPU"
+        );
+    }
+
+    #[test]
+    fn test_decode_id_token() {
+        let config = config();
+
+        let claims = serde_json::json!({
+            "iss": "https://idp.example.com/",
+            "sub": "alice",
+            "aud": "client",
+            "exp": 100,
+            "nonce": "nonce",
+        });
+
+        let token = id_token(claims.clone());
+
+        assert_eq!(
+            decode_id_token(&config, &token, "nonce", 99).unwrap().sub,
+            "alice"
+        );
+        assert!(decode_id_token(&config, &token, "nonce", 100).is_err());
+        assert!(decode_id_token(&config, &token, "other", 99).is_err());
+        assert!(decode_id_token(&config, "garbage", "nonce", 99).is_err());
+
+        let mut other = claims.clone();
+        other["iss"] = "https://evil.example.com".into();
+        assert!(decode_id_token(&config, &id_token(other), "nonce", 99).is_err());
+
+        let mut other = claims.clone();
+        other["aud"] = serde_json::json!(["client", "other"]);
+        assert!(decode_id_token(&config, &id_token(other.clone()), "nonce", 99).is_err());
+
+        other["azp"] = "client".into();
+        assert!(decode_id_token(&config, &id_token(other), "nonce", 99).is_ok());
+
+        let mut other = claims;
+        other["aud"] = "other".into();
+        assert!(decode_id_token(&config, &id_token(other), "nonce", 99).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use crate::env_param;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::SaltString;
+use argon2::{PasswordHash, PasswordHasher, PasswordVerifier};
+use once_cell::sync::Lazy;
+
+// https://cheatsheetseries.owasp.org/cheatsheets/Password_Storage_Cheat_Sheet.html#argon2id
+static PARAMS: Lazy<argon2::Params> = Lazy::new(|| {
+    argon2::Params::new(
+        env_param!("ARGON2_MEMORY_COST", argon2::Params::DEFAULT_M_COST),
+        env_param!("ARGON2_TIME_COST", argon2::Params::DEFAULT_T_COST),
+        env_param!("ARGON2_PARALLELISM", argon2::Params::DEFAULT_P_COST),
+        None,
+    )
+    .expect("invalid argon2 parameters")
+});
+
+static ARGON2: Lazy<argon2::Argon2> = Lazy::new(|| {
+    argon2::Argon2::new(
+        argon2::Algorithm::Argon2id,
+        argon2::Version::V0x13,
+        PARAMS.clone(),
+    )
+});
+
+/// Verified instead of a real hash when the user does not exist, so that unknown usernames
+/// take as long to reject as wrong passwords.
+pub static DUMMY_HASH: Lazy<String> = Lazy::new(|| {
+    let salt = SaltString::generate(&mut OsRng);
+    ARGON2.hash_password(b"dummy", &salt).unwrap().to_string()
+});
+
+// hashing is cpu and memory bound, so it must not run on (and stall) the runtime threads,
+// and a burst of logins must not spawn hundreds of blocking threads either
+static THREADS: Lazy<usize> = Lazy::new(|| env_param!("ARGON2_THREADS", num_cpus::get()));
+
+static BLOCKING_POOL: Lazy<tokio::sync::Semaphore> =
+    Lazy::new(|| tokio::sync::Semaphore::new(*THREADS));
+
+/// Hashes that are running or waiting for a thread.
+static PENDING: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+struct Pending;
+
+impl Pending {
+    #[inline(always)]
+    fn new() -> Self {
+        PENDING.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Self
+    }
+}
+
+impl Drop for Pending {
+    #[inline(always)]
+    fn drop(&mut self) {
+        PENDING.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+/// How many hashes are running or waiting per thread. Anything above 1 means that
+/// logins are queueing up.
+#[inline(always)]
+pub fn load() -> usize {
+    PENDING.load(std::sync::atomic::Ordering::Relaxed) / (*THREADS).max(1)
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Hash(argon2::password_hash::Error),
+    Join(tokio::task::JoinError),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Hash(e) => write!(f, "Failed to hash the password: {}", e),
+            Self::Join(e) => write!(f, "Failed to join the hashing task: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<argon2::password_hash::Error> for Error {
+    fn from(e: argon2::password_hash::Error) -> Self {
+        Self::Hash(e)
+    }
+}
+
+impl From<tokio::task::JoinError> for Error {
+    fn from(e: tokio::task::JoinError) -> Self {
+        Self::Join(e)
+    }
+}
+
+async fn spawn_blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, argon2::password_hash::Error> + Send + 'static,
+) -> Result<T, Error> {
+    // also counted while waiting, and if the request goes away in the meantime
+    let _pending = Pending::new();
+
+    // the semaphore is never closed
+    let _permit = BLOCKING_POOL.acquire().await.unwrap();
+
+    Ok(tokio::task::spawn_blocking(f).await??)
+}
+
+pub async fn hash(password: String) -> Result<String, Error> {
+    spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Ok(ARGON2
+            .hash_password(password.as_bytes(), &salt)?
+            .to_string())
+    })
+    .await
+}
+
+pub async fn verify(password: String, hash: String) -> Result<bool, Error> {
+    spawn_blocking(move || {
+        let hash = PasswordHash::new(&hash)?;
+        Ok(ARGON2.verify_password(password.as_bytes(), &hash).is_ok())
+    })
+    .await
+}
+
+/// Like `verify`, but for accounts that might not have a password, like the ones created
+/// through an identity provider. Nothing matches a missing password, and it takes as long
+/// to reject as a wrong one.
+pub async fn verify_optional(password: String, hash: Option<String>) -> Result<bool, Error> {
+    match hash {
+        Some(hash) => verify(password, hash).await,
+        None => {
+            verify(password, DUMMY_HASH.clone()).await?;
+            Ok(false)
+        }
+    }
+}
+
+/// Whether the hash was made with weaker parameters than the configured ones.
+pub fn needs_rehash(hash: &str) -> bool {
+    let hash = match PasswordHash::new(hash) {
+        Ok(hash) => hash,
+        Err(_) => {
+            return false;
+        }
+    };
+
+    if hash.algorithm != argon2::Algorithm::Argon2id.ident() {
+        return true;
+    }
+
+    match argon2::Params::try_from(&hash) {
+        Ok(params) => {
+            params.m_cost() < PARAMS.m_cost()
+                || params.t_cost() < PARAMS.t_cost()
+                || params.p_cost() < PARAMS.p_cost()
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_rehash() {
+        let salt = SaltString::generate(&mut OsRng);
+
+        let hash = ARGON2.hash_password(b"world", &salt).unwrap().to_string();
+        assert!(!needs_rehash(&hash));
+
+        let weak = argon2::Argon2::new(
+            argon2::Algorithm::Argon2id,
+            argon2::Version::V0x13,
+            argon2::Params::new(PARAMS.m_cost() / 2, PARAMS.t_cost(), PARAMS.p_cost(), None)
+                .unwrap(),
+        );
+
+        let hash = weak.hash_password(b"world", &salt).unwrap().to_string();
+        assert!(needs_rehash(&hash));
+
+        let argon2i = argon2::Argon2::new(
+            argon2::Algorithm::Argon2i,
+            argon2::Version::V0x13,
+            PARAMS.clone(),
+        );
+
+        let hash = argon2i.hash_password(b"world", &salt).unwrap().to_string();
+        assert!(needs_rehash(&hash));
+    }
+}
@@ -0,0 +1,29 @@
+use crate::mail::{self, Mail};
+use once_cell::sync::Lazy;
+
+/// Reset tokens expire after that many seconds.
+pub const TOKEN_LIFETIME: i64 = 60 * 60;
+
+/// At most one reset mail is sent per user within that many seconds.
+pub const RESEND_INTERVAL: i64 = 60;
+
+/// Where the token leads to, `{token}` is replaced with the token itself.
+static PASSWORD_RESET_URL: Lazy<String> = Lazy::new(|| {
+    std::env::var("PASSWORD_RESET_URL")
+        .unwrap_or(String::from("https://localhost/password_resets/{token}"))
+});
+
+pub fn send_mail(to: String, username: &str, token: &str) {
+    let url = PASSWORD_RESET_URL.replace("{token}", token);
+
+    mail::send(Mail {
+        to,
+        subject: String::from("Reset your password"),
+        body: format!(
+            "Hi {},\n\nsomebody (hopefully you) asked to reset your password. Follow the link below to choose a new one, it expires in {} minutes:\n\n{}\n\nIf it wasn't you, just ignore this mail.",
+            username,
+            TOKEN_LIFETIME / 60,
+            url
+        ),
+    });
+}
@@ -0,0 +1,103 @@
+pub const MAX_DISPLAY_NAME_LENGTH: usize = 50;
+pub const MAX_BIO_LENGTH: usize = 160;
+pub const MAX_LOCATION_LENGTH: usize = 30;
+pub const MAX_WEBSITE_LENGTH: usize = 100;
+pub const MAX_AVATAR_LENGTH: usize = 200;
+
+/// Lengths are counted in characters, so that names in other scripts aren't cut short.
+#[inline(always)]
+fn is_invalid_text(text: &str, max_length: usize, allow_newlines: bool) -> bool {
+    text.chars().count() > max_length
+        || text.trim() != text
+        || text
+            .chars()
+            .any(|char| char.is_control() && !(allow_newlines && char == '\n'))
+}
+
+#[inline(always)]
+fn is_invalid_url(url: &str, max_length: usize, schemes: &[&str]) -> bool {
+    let rest = match schemes.iter().find_map(|scheme| url.strip_prefix(scheme)) {
+        Some(rest) => rest,
+        None => {
+            return true;
+        }
+    };
+
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+
+    url.len() > max_length
+        || host.is_empty()
+        || url
+            .chars()
+            .any(|char| char.is_whitespace() || char.is_control())
+}
+
+#[inline(always)]
+pub fn is_invalid_display_name(display_name: &str) -> bool {
+    is_invalid_text(display_name, MAX_DISPLAY_NAME_LENGTH, false)
+}
+
+#[inline(always)]
+pub fn is_invalid_bio(bio: &str) -> bool {
+    is_invalid_text(bio, MAX_BIO_LENGTH, true)
+}
+
+#[inline(always)]
+pub fn is_invalid_location(location: &str) -> bool {
+    is_invalid_text(location, MAX_LOCATION_LENGTH, false)
+}
+
+#[inline(always)]
+pub fn is_invalid_website(website: &str) -> bool {
+    is_invalid_url(website, MAX_WEBSITE_LENGTH, &["https://", "http://"])
+}
+
+/// Avatars are shown on every page, so they must not be loaded over plain HTTP.
+#[inline(always)]
+pub fn is_invalid_avatar(avatar: &str) -> bool {
+    is_invalid_url(avatar, MAX_AVATAR_LENGTH, &["https://"])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::profile::*;
+
+    #[test]
+    fn test_text() {
+        assert!(!is_invalid_display_name("Hello World"));
+        assert!(!is_invalid_display_name(
+            &"é".repeat(MAX_DISPLAY_NAME_LENGTH)
+        ));
+        assert!(is_invalid_display_name(
+            &"é".repeat(MAX_DISPLAY_NAME_LENGTH + 1)
+        ));
+        assert!(is_invalid_display_name(" Hello"));
+        assert!(is_invalid_display_name("Hello\nWorld"));
+        assert!(is_invalid_display_name("Hello\u{0}"));
+
+        assert!(!is_invalid_bio("Hello\nWorld"));
+        assert!(is_invalid_bio("Hello\tWorld"));
+        assert!(is_invalid_bio(&"a".repeat(MAX_BIO_LENGTH + 1)));
+
+        assert!(!is_invalid_location("Berlin"));
+        assert!(is_invalid_location(&"a".repeat(MAX_LOCATION_LENGTH + 1)));
+    }
+
+    #[test]
+    fn test_url() {
+        assert!(!is_invalid_website("https://example.com"));
+        assert!(!is_invalid_website("http://example.com/about?lang=en"));
+        assert!(is_invalid_website("example.com"));
+        assert!(is_invalid_website("javascript:alert(1)"));
+        assert!(is_invalid_website("https://"));
+        assert!(is_invalid_website("https:///path"));
+        assert!(is_invalid_website("https://example.com/a b"));
+        assert!(is_invalid_website(&format!(
+            "https://{}",
+            "a".repeat(MAX_WEBSITE_LENGTH)
+        )));
+
+        assert!(!is_invalid_avatar("https://example.com/avatar.png"));
+        assert!(is_invalid_avatar("http://example.com/avatar.png"));
+    }
+}
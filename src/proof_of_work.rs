@@ -0,0 +1,227 @@
+use crate::common::*;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use once_cell::sync::Lazy;
+use sha2::{Digest, Sha256};
+
+/// How many leading zero bits the hash of a solution needs at the least, `0` turns challenges off.
+/// Every further bit doubles the work of the client.
+pub static DIFFICULTY: Lazy<u32> = Lazy::new(|| env_param!("PROOF_OF_WORK_DIFFICULTY", 0));
+
+/// The difficulty never rises above that, so that slow devices can still log in under load.
+static MAX_DIFFICULTY: Lazy<u32> = Lazy::new(|| env_param!("PROOF_OF_WORK_MAX_DIFFICULTY", 24));
+
+/// Challenges expire after that many seconds.
+pub const LIFETIME: i64 = 5 * 60;
+
+const NONCE_LENGTH: usize = 16;
+
+// the nonce, the expiry time and the difficulty
+const CHALLENGE_LENGTH: usize = NONCE_LENGTH + 8 + 1;
+
+const MAX_SOLUTION_LENGTH: usize = 64;
+
+pub struct Challenge {
+    pub token: String,
+    pub difficulty: u32,
+    pub time_expires: i64,
+}
+
+#[inline(always)]
+fn bit_length(n: usize) -> u32 {
+    usize::BITS - n.leading_zeros()
+}
+
+/// Every doubling of the load of the server, or of the failed logins from the address, adds a bit.
+#[inline(always)]
+pub fn difficulty(base: u32, max: u32, load: usize, failures: usize) -> u32 {
+    (base + bit_length(load) + bit_length(failures)).min(max.max(base))
+}
+
+#[inline(always)]
+pub fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+
+    for byte in hash {
+        bits += byte.leading_zeros();
+
+        if *byte != 0 {
+            break;
+        }
+    }
+
+    bits
+}
+
+/// A solution is any string that makes the SHA-256 hash of `{challenge}:{solution}`
+/// start with enough zero bits.
+#[inline(always)]
+pub fn is_solution(token: &str, solution: &str, difficulty: u32) -> bool {
+    if solution.len() > MAX_SOLUTION_LENGTH {
+        return false;
+    }
+
+    let hash = Sha256::new()
+        .chain_update(token)
+        .chain_update(b":")
+        .chain_update(solution)
+        .finalize();
+
+    leading_zero_bits(&hash) >= difficulty
+}
+
+#[inline(always)]
+fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+/// The difficulty the current situation calls for, given the load and the failed logins from the address.
+async fn current_difficulty(
+    database: &Database,
+    address: std::net::IpAddr,
+) -> std::result::Result<u32, sqlx::Error> {
+    let failures = throttle::ip_failure_count(database, address).await?;
+
+    Ok(difficulty(
+        *DIFFICULTY,
+        *MAX_DIFFICULTY,
+        password::load(),
+        failures.max(0) as usize,
+    ))
+}
+
+/// Hands out a challenge for the address, as hard as the current situation calls for.
+pub async fn issue(
+    database: &Database,
+    address: std::net::IpAddr,
+) -> std::result::Result<Challenge, sqlx::Error> {
+    let difficulty = current_difficulty(database, address).await?;
+
+    let time_expires = now() + LIFETIME;
+
+    let mut challenge = [0u8; CHALLENGE_LENGTH];
+    OsRng.fill_bytes(&mut challenge[..NONCE_LENGTH]);
+    challenge[NONCE_LENGTH..NONCE_LENGTH + 8].copy_from_slice(&time_expires.to_le_bytes());
+    challenge[NONCE_LENGTH + 8] = difficulty.min(u8::MAX as u32) as u8;
+
+    Ok(Challenge {
+        token: auth::sign_proof_of_work(&challenge, address),
+        difficulty,
+        time_expires,
+    })
+}
+
+/// Checks the solution and uses the challenge up. Always `true` if challenges are turned off.
+pub async fn verify(
+    database: &Database,
+    address: std::net::IpAddr,
+    token: Option<&str>,
+    solution: Option<&str>,
+) -> std::result::Result<bool, sqlx::Error> {
+    if *DIFFICULTY == 0 {
+        return Ok(true);
+    }
+
+    let (token, solution) = match (token, solution) {
+        (Some(token), Some(solution)) => (token, solution),
+        _ => {
+            return Ok(false);
+        }
+    };
+
+    let challenge = match auth::verify_proof_of_work(token, address) {
+        Some(challenge) if challenge.len() == CHALLENGE_LENGTH => challenge,
+        _ => {
+            return Ok(false);
+        }
+    };
+
+    let mut time_expires = [0u8; 8];
+    time_expires.copy_from_slice(&challenge[NONCE_LENGTH..NONCE_LENGTH + 8]);
+    let time_expires = i64::from_le_bytes(time_expires);
+
+    let difficulty = challenge[NONCE_LENGTH + 8] as u32;
+
+    if time_expires <= now() || !is_solution(token, solution, difficulty) {
+        return Ok(false);
+    }
+
+    // the load and the failures only decide how hard new challenges are. They change from moment
+    // to moment, and refusing solved challenges whenever they rose would shut out honest clients
+    // right when the gate is busiest. The base difficulty might have been raised meanwhile though
+    if difficulty < *DIFFICULTY {
+        return Ok(false);
+    }
+
+    // every challenge can only be solved once
+    let result = sqlx::query(
+        "INSERT INTO spent_challenges (nonce, time_expires) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+    )
+    .bind(base64::encode_config(
+        &challenge[..NONCE_LENGTH],
+        base64::URL_SAFE_NO_PAD,
+    ))
+    .bind(time_expires)
+    .execute(database)
+    .await?;
+
+    Ok(result.rows_affected() == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_difficulty() {
+        use crate::proof_of_work::difficulty;
+
+        assert_eq!(difficulty(16, 24, 0, 0), 16);
+        assert_eq!(difficulty(16, 24, 1, 0), 17);
+        assert_eq!(difficulty(16, 24, 3, 0), 18);
+        assert_eq!(difficulty(16, 24, 0, 20), 21);
+        assert_eq!(difficulty(16, 24, 1000, 1000), 24);
+        // the maximum can't make it easier than the base
+        assert_eq!(difficulty(16, 8, 1000, 0), 16);
+    }
+
+    #[test]
+    fn test_leading_zero_bits() {
+        use crate::proof_of_work::leading_zero_bits;
+
+        assert_eq!(leading_zero_bits(&[0xff, 0x00]), 0);
+        assert_eq!(leading_zero_bits(&[0x00, 0x10]), 11);
+        assert_eq!(leading_zero_bits(&[0x00, 0x00, 0x01]), 23);
+        assert_eq!(leading_zero_bits(&[0x00, 0x00]), 16);
+    }
+
+    #[test]
+    fn test_is_solution() {
+        use crate::proof_of_work::is_solution;
+
+        // sha256("challenge:1") starts with 0x29, "challenge:4" with 0xc6 and "challenge:9" with 0x0e
+        assert!(is_solution("challenge", "1", 2));
+        assert!(!is_solution("challenge", "1", 3));
+        assert!(is_solution("challenge", "4", 0));
+        assert!(!is_solution("challenge", "4", 1));
+        assert!(is_solution("challenge", "9", 4));
+        assert!(!is_solution("challenge", "9", 5));
+        assert!(!is_solution("challenge", &"1".repeat(65), 0));
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        use crate::auth::{sign_proof_of_work, verify_proof_of_work};
+
+        let address = "127.0.0.1".parse().unwrap();
+        let token = sign_proof_of_work(b"challenge", address);
+
+        assert_eq!(
+            verify_proof_of_work(&token, address),
+            Some(b"challenge".to_vec())
+        );
+        assert!(verify_proof_of_work(&token, "127.0.0.2".parse().unwrap()).is_none());
+        assert!(verify_proof_of_work(&token[1..], address).is_none());
+        assert!(verify_proof_of_work("", address).is_none());
+    }
+}
@@ -0,0 +1,67 @@
+use crate::common::*;
+use once_cell::sync::Lazy;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    Open,
+    InviteOnly,
+    Closed,
+}
+
+impl std::str::FromStr for Mode {
+    type Err = ();
+
+    fn from_str(val: &str) -> std::result::Result<Self, Self::Err> {
+        match val {
+            "open" => Ok(Self::Open),
+            "invite_only" => Ok(Self::InviteOnly),
+            "closed" => Ok(Self::Closed),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Who can sign up. Accounts of existing users keep working in every mode.
+pub static MODE: Lazy<Mode> = Lazy::new(|| {
+    std::env::var("REGISTRATION_MODE")
+        .ok()
+        .map(|mode| mode.parse().expect("Invalid REGISTRATION_MODE"))
+        .unwrap_or(Mode::Open)
+});
+
+/// How many people a user can invite, unless the user has a quota of their own.
+/// Uses of expired invites that nobody redeemed are given back.
+pub static INVITE_QUOTA: Lazy<i32> = Lazy::new(|| env_param!("INVITE_QUOTA", 5));
+
+/// Invites expire after that many seconds, unless the creator asks for less.
+pub const MAX_INVITE_LIFETIME: i64 = 30 * 24 * 60 * 60;
+
+pub const MAX_INVITE_USES: i32 = 100;
+
+/// Takes one use of the invite within the transaction of the sign up. Evaluates to the invite
+/// and the user who created it, or `None` if the invite is unknown, expired or used up.
+pub async fn redeem_invite<'a>(
+    executor: impl sqlx::PgExecutor<'a>,
+    code: &str,
+) -> std::result::Result<Option<(i64, i64)>, sqlx::Error> {
+    let row = sqlx::query("UPDATE invites SET use_count = use_count + 1 WHERE code = $1 AND use_count < max_uses AND time_expires > extract(EPOCH FROM now()) RETURNING id, creator_id")
+        .bind(code)
+        .fetch_optional(executor)
+        .await?;
+
+    Ok(row.map(|row| (row.get_unchecked(0), row.get_unchecked(1))))
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_mode() {
+        use crate::registration::Mode;
+
+        assert_eq!("open".parse(), Ok(Mode::Open));
+        assert_eq!("invite_only".parse(), Ok(Mode::InviteOnly));
+        assert_eq!("closed".parse(), Ok(Mode::Closed));
+        assert_eq!("Open".parse::<Mode>(), Err(()));
+        assert_eq!("invite".parse::<Mode>(), Err(()));
+    }
+}
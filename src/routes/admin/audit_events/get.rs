@@ -0,0 +1,95 @@
+use crate::common::*;
+
+pub async fn get(request: &mut Request, database: Database) -> Result {
+    // the trail has the addresses of everyone, staff included
+    check_role!(request, database, auth::Role::Admin);
+
+    let mut limit = 50;
+    let mut offset = 0;
+    let mut actor_id: Option<i64> = None;
+    let mut target_user_id: Option<i64> = None;
+    let mut target_tweet_id: Option<i64> = None;
+    let mut action = None;
+    let mut request_id = None;
+    let mut since: Option<i64> = None;
+    let mut until: Option<i64> = None;
+
+    macro_rules! parse {
+        ($value:ident) => {
+            match $value.parse() {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    return Ok(Response::bad_request());
+                }
+            }
+        };
+    }
+
+    if let Some(query) = request.uri().query() {
+        for pair in query.split('&') {
+            let (key, value) = match pair.split_once('=') {
+                Some(pair) => pair,
+                None => {
+                    return Ok(Response::bad_request());
+                }
+            };
+
+            match key {
+                "limit" => match value.parse::<i32>() {
+                    Ok(value) if (0..=50).contains(&value) => limit = value,
+                    _ => {
+                        return Ok(Response::bad_request());
+                    }
+                },
+                "offset" => match value.parse::<i32>() {
+                    Ok(value) if value >= 0 => offset = value,
+                    _ => {
+                        return Ok(Response::bad_request());
+                    }
+                },
+                "actor_id" => actor_id = parse!(value),
+                "target_user_id" => target_user_id = parse!(value),
+                "target_tweet_id" => target_tweet_id = parse!(value),
+                // actions and request ids only have characters that need no decoding
+                "action" => action = Some(value),
+                "request_id" => request_id = Some(value),
+                "since" => since = parse!(value),
+                "until" => until = parse!(value),
+                _ => {
+                    return Ok(Response::bad_request());
+                }
+            }
+        }
+    }
+
+    let result = sqlx::query("SELECT id, actor_id, action, target_user_id, target_tweet_id, details, host(ip_address), user_agent, request_id, time_created FROM audit_events WHERE ($1::BIGINT IS NULL OR actor_id = $1) AND ($2::BIGINT IS NULL OR target_user_id = $2) AND ($3::BIGINT IS NULL OR target_tweet_id = $3) AND ($4::TEXT IS NULL OR action = $4) AND ($5::TEXT IS NULL OR request_id = $5) AND ($6::BIGINT IS NULL OR time_created >= $6) AND ($7::BIGINT IS NULL OR time_created < $7) ORDER BY id DESC LIMIT $8 OFFSET $9")
+        .bind(actor_id)
+        .bind(target_user_id)
+        .bind(target_tweet_id)
+        .bind(action)
+        .bind(request_id)
+        .bind(since)
+        .bind(until)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&database)
+        .await;
+
+    let response = unwrap_internal_error!(result)
+        .into_iter()
+        .map(|row| AuditEvent {
+            id: row.get_unchecked(0),
+            actor_id: row.get_unchecked(1),
+            action: row.get_unchecked(2),
+            target_user_id: row.get_unchecked(3),
+            target_tweet_id: row.get_unchecked(4),
+            details: row.get_unchecked(5),
+            ip_address: row.get_unchecked(6),
+            user_agent: row.get_unchecked(7),
+            request_id: row.get_unchecked(8),
+            time_created: row.get_unchecked(9),
+        })
+        .collect::<Vec<AuditEvent>>();
+
+    Ok((StatusCode::OK, Response::success(response)))
+}
@@ -0,0 +1,3 @@
+pub mod audit_events;
+pub mod tweets;
+pub mod users;
@@ -0,0 +1,53 @@
+use crate::common::*;
+
+pub async fn delete(request: &mut Request, database: Database) -> Result {
+    let id = parse_path_var!(request, i64);
+    let (actor_id, actor_role) = check_role!(request, database, auth::Role::Moderator);
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    let result = sqlx::query("SELECT tweets.user_id, users.role FROM tweets JOIN users ON users.id = tweets.user_id WHERE tweets.id = $1 FOR UPDATE OF tweets")
+        .bind(id)
+        .fetch_optional(&mut transaction)
+        .await;
+
+    let (user_id, role) = match unwrap_internal_error!(result) {
+        Some(row) => (
+            row.get_unchecked::<i64, _>(0),
+            row.get_unchecked::<&str, _>(1)
+                .parse()
+                .unwrap_or(auth::Role::User),
+        ),
+        None => {
+            return Ok(Response::not_found());
+        }
+    };
+
+    // moderators can't delete the tweets of their peers or superiors
+    if !admin::can_delete_tweet(actor_id, actor_role, user_id, role) {
+        return Ok(Response::forbidden());
+    }
+
+    let result = sqlx::query("DELETE FROM tweets WHERE id = $1")
+        .bind(id)
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+
+    let result = admin::record_action(
+        &mut transaction,
+        request,
+        actor_id,
+        "delete_tweet",
+        Some(user_id),
+        Some(id),
+        None,
+    )
+    .await;
+
+    unwrap_internal_error!(result);
+    unwrap_internal_error!(transaction.commit().await);
+
+    Ok((StatusCode::OK, Response::empty()))
+}
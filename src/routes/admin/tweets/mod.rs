@@ -0,0 +1,3 @@
+mod delete;
+
+pub use delete::delete;
@@ -0,0 +1,92 @@
+use crate::common::*;
+
+pub async fn get(request: &mut Request, database: Database) -> Result {
+    check_role!(request, database, auth::Role::Moderator);
+
+    let mut limit = 50;
+    let mut offset = 0;
+    let mut role = None;
+    let mut suspended = None;
+    let mut username = None;
+    let mut invited_by: Option<i64> = None;
+
+    if let Some(query) = request.uri().query() {
+        for pair in query.split('&') {
+            let (key, value) = match pair.split_once('=') {
+                Some(pair) => pair,
+                None => {
+                    return Ok(Response::bad_request());
+                }
+            };
+
+            match key {
+                "limit" => match value.parse::<i32>() {
+                    Ok(value) if (0..=50).contains(&value) => limit = value,
+                    _ => {
+                        return Ok(Response::bad_request());
+                    }
+                },
+                "offset" => match value.parse::<i32>() {
+                    Ok(value) if value >= 0 => offset = value,
+                    _ => {
+                        return Ok(Response::bad_request());
+                    }
+                },
+                "role" => match value.parse::<auth::Role>() {
+                    Ok(value) => role = Some(value.as_str()),
+                    Err(_) => {
+                        return Ok(Response::bad_request());
+                    }
+                },
+                "suspended" => match value.parse::<bool>() {
+                    Ok(value) => suspended = Some(value),
+                    Err(_) => {
+                        return Ok(Response::bad_request());
+                    }
+                },
+                // usernames only have characters that need no decoding
+                "username" => username = Some(username::normalize(value)),
+                "invited_by" => match value.parse() {
+                    Ok(value) => invited_by = Some(value),
+                    Err(_) => {
+                        return Ok(Response::bad_request());
+                    }
+                },
+                _ => {
+                    return Ok(Response::bad_request());
+                }
+            }
+        }
+    }
+
+    let result = sqlx::query("SELECT id, username, email, email_verified, role, time_deactivated, time_suspended, suspension_reason, invited_by, coalesce(invite_quota, $6) FROM users WHERE ($1::TEXT IS NULL OR role = $1) AND ($2::BOOLEAN IS NULL OR (time_suspended IS NOT NULL) = $2) AND ($3::TEXT IS NULL OR starts_with(lower(username), lower($3))) AND ($7::BIGINT IS NULL OR invited_by = $7) ORDER BY id LIMIT $4 OFFSET $5")
+        .bind(role)
+        .bind(suspended)
+        .bind(username)
+        .bind(limit)
+        .bind(offset)
+        .bind(*registration::INVITE_QUOTA)
+        .bind(invited_by)
+        .fetch_all(&database)
+        .await;
+
+    let response = unwrap_internal_error!(result)
+        .into_iter()
+        .map(|row| {
+            json!({
+                "id": row.get_unchecked::<i64, _>(0),
+                "username": row.get_unchecked::<String, _>(1),
+                "email": row.get_unchecked::<Option<String>, _>(2),
+                "email_verified": row.get_unchecked::<bool, _>(3),
+                "role": row.get_unchecked::<String, _>(4),
+                "time_deactivated": row.get_unchecked::<Option<i64>, _>(5),
+                "time_suspended": row.get_unchecked::<Option<i64>, _>(6),
+                "suspension_reason": row.get_unchecked::<Option<String>, _>(7),
+                "invited_by": row.get_unchecked::<Option<i64>, _>(8),
+                "invite_quota": row.get_unchecked::<i32, _>(9),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok((StatusCode::OK, Response::success(response)))
+}
@@ -0,0 +1,80 @@
+use crate::common::*;
+
+pub async fn put(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    let user_id = parse_path_var!(request, i64, 1);
+    let (actor_id, actor_role) = check_role!(request, database, auth::Role::Admin);
+
+    // `null` falls back to INVITE_QUOTA
+    #[derive(serde::Deserialize)]
+    struct Body {
+        invite_quota: Option<i32>,
+    }
+
+    let body = body!(request, Body);
+
+    if matches!(body.invite_quota, Some(quota) if quota < 0) {
+        return Ok(Response::bad_request());
+    }
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    let result = sqlx::query("SELECT role, invite_quota FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .fetch_optional(&mut transaction)
+        .await;
+
+    let row = match unwrap_internal_error!(result) {
+        Some(row) => row,
+        None => {
+            return Ok(Response::not_found());
+        }
+    };
+
+    let role = row
+        .get_unchecked::<&str, _>(0)
+        .parse()
+        .unwrap_or(auth::Role::User);
+
+    if !admin::can_manage(actor_id, actor_role, user_id, role) {
+        return Ok(Response::forbidden());
+    }
+
+    let quota = row.get_unchecked::<Option<i32>, _>(1);
+
+    if quota == body.invite_quota {
+        return Ok((StatusCode::OK, Response::empty()));
+    }
+
+    let result = sqlx::query("UPDATE users SET invite_quota = $1 WHERE id = $2")
+        .bind(body.invite_quota)
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+
+    let describe = |quota: Option<i32>| match quota {
+        Some(quota) => quota.to_string(),
+        None => String::from("default"),
+    };
+
+    let details = format!("{} -> {}", describe(quota), describe(body.invite_quota));
+
+    let result = admin::record_action(
+        &mut transaction,
+        request,
+        actor_id,
+        "change_invite_quota",
+        Some(user_id),
+        None,
+        Some(&details),
+    )
+    .await;
+
+    unwrap_internal_error!(result);
+    unwrap_internal_error!(transaction.commit().await);
+
+    Ok((StatusCode::OK, Response::empty()))
+}
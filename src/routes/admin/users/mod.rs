@@ -0,0 +1,8 @@
+pub mod invite_quota;
+pub mod role;
+pub mod sessions;
+pub mod suspension;
+
+mod get;
+
+pub use get::get;
@@ -0,0 +1,74 @@
+use crate::common::*;
+
+pub async fn put(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    let user_id = parse_path_var!(request, i64, 1);
+    let (actor_id, actor_role) = check_role!(request, database, auth::Role::Admin);
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        role: String,
+    }
+
+    let body = body!(request, Body);
+
+    let new_role = match body.role.parse::<auth::Role>() {
+        Ok(role) => role,
+        Err(_) => {
+            return Ok(Response::bad_request());
+        }
+    };
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    let result = sqlx::query("SELECT role FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .fetch_optional(&mut transaction)
+        .await;
+
+    let role = match unwrap_internal_error!(result) {
+        Some(row) => row
+            .get_unchecked::<&str, _>(0)
+            .parse()
+            .unwrap_or(auth::Role::User),
+        None => {
+            return Ok(Response::not_found());
+        }
+    };
+
+    // admins can promote others to admin, but can't demote each other
+    if !admin::can_manage(actor_id, actor_role, user_id, role) {
+        return Ok(Response::forbidden());
+    }
+
+    if role == new_role {
+        return Ok((StatusCode::OK, Response::empty()));
+    }
+
+    let result = sqlx::query("UPDATE users SET role = $1 WHERE id = $2")
+        .bind(new_role.as_str())
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+
+    let details = format!("{} -> {}", role.as_str(), new_role.as_str());
+
+    let result = admin::record_action(
+        &mut transaction,
+        request,
+        actor_id,
+        "change_role",
+        Some(user_id),
+        None,
+        Some(&details),
+    )
+    .await;
+
+    unwrap_internal_error!(result);
+    unwrap_internal_error!(transaction.commit().await);
+
+    Ok((StatusCode::OK, Response::empty()))
+}
@@ -0,0 +1,53 @@
+use crate::common::*;
+
+pub async fn delete(request: &mut Request, database: Database) -> Result {
+    let user_id = parse_path_var!(request, i64, 1);
+    let (actor_id, actor_role) = check_role!(request, database, auth::Role::Admin);
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    let result = sqlx::query("SELECT role FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .fetch_optional(&mut transaction)
+        .await;
+
+    let role = match unwrap_internal_error!(result) {
+        Some(row) => row
+            .get_unchecked::<&str, _>(0)
+            .parse()
+            .unwrap_or(auth::Role::User),
+        None => {
+            return Ok(Response::not_found());
+        }
+    };
+
+    if !admin::can_manage(actor_id, actor_role, user_id, role) {
+        return Ok(Response::forbidden());
+    }
+
+    let result = sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await;
+
+    let details = format!(
+        "{} sessions",
+        unwrap_internal_error!(result).rows_affected()
+    );
+
+    let result = admin::record_action(
+        &mut transaction,
+        request,
+        actor_id,
+        "revoke_sessions",
+        Some(user_id),
+        None,
+        Some(&details),
+    )
+    .await;
+
+    unwrap_internal_error!(result);
+    unwrap_internal_error!(transaction.commit().await);
+
+    Ok((StatusCode::OK, Response::empty()))
+}
@@ -0,0 +1,59 @@
+use crate::common::*;
+
+pub async fn delete(request: &mut Request, database: Database) -> Result {
+    let user_id = parse_path_var!(request, i64, 1);
+    let (actor_id, actor_role) = check_role!(request, database, auth::Role::Moderator);
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    let result =
+        sqlx::query("SELECT role, time_suspended IS NOT NULL FROM users WHERE id = $1 FOR UPDATE")
+            .bind(user_id)
+            .fetch_optional(&mut transaction)
+            .await;
+
+    let row = match unwrap_internal_error!(result) {
+        Some(row) => row,
+        None => {
+            return Ok(Response::not_found());
+        }
+    };
+
+    let role = row
+        .get_unchecked::<&str, _>(0)
+        .parse()
+        .unwrap_or(auth::Role::User);
+
+    if !admin::can_manage(actor_id, actor_role, user_id, role) {
+        return Ok(Response::forbidden());
+    }
+
+    if !row.get_unchecked::<bool, _>(1) {
+        return Ok((StatusCode::OK, Response::empty()));
+    }
+
+    let result = sqlx::query(
+        "UPDATE users SET time_suspended = NULL, suspension_reason = NULL WHERE id = $1",
+    )
+    .bind(user_id)
+    .execute(&mut transaction)
+    .await;
+
+    unwrap_internal_error!(result);
+
+    let result = admin::record_action(
+        &mut transaction,
+        request,
+        actor_id,
+        "unsuspend_user",
+        Some(user_id),
+        None,
+        None,
+    )
+    .await;
+
+    unwrap_internal_error!(result);
+    unwrap_internal_error!(transaction.commit().await);
+
+    Ok((StatusCode::OK, Response::empty()))
+}
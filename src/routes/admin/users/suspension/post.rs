@@ -0,0 +1,72 @@
+use crate::common::*;
+
+pub async fn post(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    let user_id = parse_path_var!(request, i64, 1);
+    let (actor_id, actor_role) = check_role!(request, database, auth::Role::Moderator);
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        reason: Option<String>,
+    }
+
+    let body = body!(request, Body);
+
+    if matches!(&body.reason, Some(reason) if reason.len() > 512) {
+        return Ok(Response::bad_request());
+    }
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    let result = sqlx::query("SELECT role FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .fetch_optional(&mut transaction)
+        .await;
+
+    let role = match unwrap_internal_error!(result) {
+        Some(row) => row
+            .get_unchecked::<&str, _>(0)
+            .parse()
+            .unwrap_or(auth::Role::User),
+        None => {
+            return Ok(Response::not_found());
+        }
+    };
+
+    if !admin::can_manage(actor_id, actor_role, user_id, role) {
+        return Ok(Response::forbidden());
+    }
+
+    // suspending again only updates the reason
+    let result = sqlx::query("UPDATE users SET time_suspended = coalesce(time_suspended, extract(EPOCH FROM now())), suspension_reason = $1 WHERE id = $2")
+        .bind(&body.reason)
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+
+    let result = sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+
+    let result = admin::record_action(
+        &mut transaction,
+        request,
+        actor_id,
+        "suspend_user",
+        Some(user_id),
+        None,
+        body.reason.as_deref(),
+    )
+    .await;
+
+    unwrap_internal_error!(result);
+    unwrap_internal_error!(transaction.commit().await);
+
+    Ok((StatusCode::OK, Response::empty()))
+}
@@ -0,0 +1,19 @@
+use crate::common::*;
+
+pub async fn get(request: &mut Request, database: Database) -> Result {
+    if *proof_of_work::DIFFICULTY == 0 {
+        return Ok(Response::not_found());
+    }
+
+    let client = client!(request);
+
+    let challenge = unwrap_internal_error!(proof_of_work::issue(&database, client.address).await);
+
+    let response = json!({
+        "challenge": challenge.token,
+        "difficulty": challenge.difficulty,
+        "time_expires": challenge.time_expires,
+    });
+
+    Ok((StatusCode::OK, Response::success(response)))
+}
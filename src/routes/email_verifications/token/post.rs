@@ -0,0 +1,79 @@
+use crate::common::*;
+
+pub async fn post(request: &mut Request, database: Database) -> Result {
+    let token = parse_path_var!(request, String);
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    let result = sqlx::query("DELETE FROM email_verifications WHERE token_hash = $1 AND time_created > extract(EPOCH FROM now()) - $2 RETURNING user_id, email")
+        .bind(auth::hash_random_token(&token))
+        .bind(email::VERIFICATION_LIFETIME)
+        .fetch_optional(&mut transaction)
+        .await;
+
+    let row = match unwrap_internal_error!(result) {
+        Some(row) => row,
+        None => {
+            return Ok(Response::not_found());
+        }
+    };
+
+    let user_id = row.get_unchecked::<i64, _>(0);
+    let email = row.get_unchecked::<String, _>(1);
+
+    // a pending change only takes effect now, the old address is returned to notify it
+    let result = sqlx::query("UPDATE users SET email = $2, email_verified = true FROM (SELECT id, username, email FROM users WHERE id = $1 FOR UPDATE) AS old WHERE users.id = old.id RETURNING old.username, old.email")
+        .bind(user_id)
+        .bind(&email)
+        .fetch_optional(&mut transaction)
+        .await;
+
+    // somebody else verified the address first
+    if let Err(sqlx::Error::Database(e)) = &result {
+        if e.code().as_deref() == Some("23505") {
+            return Ok((
+                StatusCode::CONFLICT,
+                Response::error("Email already exists"),
+            ));
+        }
+    }
+
+    let row = match unwrap_internal_error!(result) {
+        Some(row) => row,
+        None => {
+            return Ok(Response::not_found());
+        }
+    };
+
+    let username = row.get_unchecked::<String, _>(0);
+    let old_email = row.get_unchecked::<Option<String>, _>(1);
+
+    // the first address of the user doesn't replace anything
+    let changed =
+        matches!(old_email.as_deref(), Some(old_email) if !old_email.eq_ignore_ascii_case(&email));
+
+    if changed {
+        let result = audit::record_event(&mut transaction, user_id, "email_changed", request).await;
+
+        unwrap_internal_error!(result);
+    }
+
+    let result = audit::record_event(&mut transaction, user_id, "email_verified", request).await;
+
+    unwrap_internal_error!(result);
+    unwrap_internal_error!(transaction.commit().await);
+
+    // let the owner of the old address know, in case it wasn't them
+    if let Some(old_email) = old_email.filter(|_| changed) {
+        mail::send(mail::Mail {
+            to: old_email,
+            subject: String::from("Your email address was changed"),
+            body: format!(
+                "Hi {},\n\nthe email address of your account was changed to {}. If it wasn't you, reset your password right away.",
+                username, email
+            ),
+        });
+    }
+
+    Ok((StatusCode::OK, Response::empty()))
+}
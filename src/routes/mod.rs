@@ -1 +1,7 @@
+pub mod admin;
+pub mod challenges;
+pub mod email_verifications;
+pub mod password_resets;
+pub mod search;
+pub mod tweets;
 pub mod users;
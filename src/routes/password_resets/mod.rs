@@ -0,0 +1,5 @@
+pub mod token;
+
+mod post;
+
+pub use post::post;
@@ -0,0 +1,110 @@
+use crate::common::*;
+
+pub async fn post(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        username: Option<String>,
+        email: Option<String>,
+    }
+
+    let body = body!(request, Body);
+
+    let result = match (&body.username, &body.email) {
+        (Some(username), None) => {
+            sqlx::query("SELECT id, username, email, email_verified FROM users WHERE lower(username) = lower($1) AND (time_deactivated IS NULL OR time_deactivated > extract(EPOCH FROM now()) - $2)")
+                .bind(username::normalize(username))
+                .bind(*ACCOUNT_GRACE_PERIOD)
+                .fetch_optional(&database)
+                .await
+        }
+        (None, Some(email)) => {
+            sqlx::query("SELECT id, username, email, email_verified FROM users WHERE lower(email) = lower($1) AND email_verified AND (time_deactivated IS NULL OR time_deactivated > extract(EPOCH FROM now()) - $2)")
+                .bind(email)
+                .bind(*ACCOUNT_GRACE_PERIOD)
+                .fetch_optional(&database)
+                .await
+        }
+        _ => {
+            return Ok(Response::bad_request());
+        }
+    };
+
+    // the response is the same whether the account exists or not, so that it can't be
+    // used to find out who is registered
+    let row = match unwrap_internal_error!(result) {
+        Some(row) => row,
+        None => {
+            return Ok((StatusCode::ACCEPTED, Response::empty()));
+        }
+    };
+
+    let user_id = row.get_unchecked::<i64, _>(0);
+
+    // the token would give the account to whoever controls the address, so it has to be the owner
+    let email = match row.get_unchecked::<Option<String>, _>(2) {
+        Some(email) if row.get_unchecked::<bool, _>(3) => email,
+        _ => {
+            log::info!(
+                "User {} asked for a password reset but has no verified email",
+                user_id
+            );
+            return Ok((StatusCode::ACCEPTED, Response::empty()));
+        }
+    };
+
+    let token = auth::generate_random_token();
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    // serialize concurrent requests for the same user, so that the rate limit holds
+    let result = sqlx::query("SELECT FROM users WHERE id = $1 FOR UPDATE")
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+
+    let result = sqlx::query("SELECT EXISTS (SELECT FROM password_resets WHERE user_id = $1 AND time_created > extract(EPOCH FROM now()) - $2)")
+        .bind(user_id)
+        .bind(password_reset::RESEND_INTERVAL)
+        .fetch_one(&mut transaction)
+        .await;
+
+    if unwrap_internal_error!(result).get_unchecked::<bool, _>(0) {
+        return Ok((StatusCode::ACCEPTED, Response::empty()));
+    }
+
+    // only the latest token is valid
+    let result = sqlx::query("DELETE FROM password_resets WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+
+    let result = sqlx::query("INSERT INTO password_resets (user_id, token_hash) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind(auth::hash_random_token(&token))
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+
+    // anybody can ask for a reset, so there is no actor
+    let event = audit::Event {
+        action: "password_reset_requested",
+        actor_id: None,
+        target_user_id: Some(user_id),
+        target_tweet_id: None,
+        details: None,
+    };
+
+    unwrap_internal_error!(audit::record(&mut transaction, event, request).await);
+    unwrap_internal_error!(transaction.commit().await);
+
+    password_reset::send_mail(email, row.get_unchecked(1), &token);
+
+    Ok((StatusCode::ACCEPTED, Response::empty()))
+}
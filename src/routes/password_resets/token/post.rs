@@ -0,0 +1,76 @@
+use crate::common::*;
+
+pub async fn post(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    let token = parse_path_var!(request, String);
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        password: String,
+    }
+
+    let body = body!(request, Body);
+
+    if Credentials::is_invalid_password(&body.password) {
+        return Ok(Response::bad_request());
+    }
+
+    let token_hash = auth::hash_random_token(&token);
+
+    let result = sqlx::query("SELECT EXISTS (SELECT FROM password_resets WHERE token_hash = $1 AND time_used IS NULL AND time_created > extract(EPOCH FROM now()) - $2)")
+        .bind(&token_hash)
+        .bind(password_reset::TOKEN_LIFETIME)
+        .fetch_one(&database)
+        .await;
+
+    // checked first, so that made up tokens don't get anyone a free run of the slow hash
+    if !unwrap_internal_error!(result).get_unchecked::<bool, _>(0) {
+        return Ok(Response::not_found());
+    }
+
+    // hashed before the transaction, so that waiting for a hashing thread holds no connection
+    let hash = unwrap_internal_error!(password::hash(body.password).await);
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    // marking the token as used in the same statement makes it single-use, even under concurrency
+    let result = sqlx::query("UPDATE password_resets SET time_used = extract(EPOCH FROM now()) WHERE token_hash = $1 AND time_used IS NULL AND time_created > extract(EPOCH FROM now()) - $2 RETURNING user_id")
+        .bind(&token_hash)
+        .bind(password_reset::TOKEN_LIFETIME)
+        .fetch_optional(&mut transaction)
+        .await;
+
+    let user_id = match unwrap_internal_error!(result) {
+        Some(row) => row.get_unchecked::<i64, _>(0),
+        None => {
+            return Ok(Response::not_found());
+        }
+    };
+
+    let result =
+        sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2 RETURNING username")
+            .bind(hash)
+            .bind(user_id)
+            .fetch_one(&mut transaction)
+            .await;
+
+    let username = unwrap_internal_error!(result).get_unchecked::<String, _>(0);
+
+    // whoever knew the old password might still be logged in somewhere
+    let result = sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+    unwrap_internal_error!(transaction.commit().await);
+
+    // the owner proved control of the account, so they should be able to log in right away
+    unwrap_internal_error!(throttle::record_success(&database, &username).await);
+    unwrap_internal_error!(
+        audit::record_event(&database, user_id, "password_reset", request).await
+    );
+
+    Ok((StatusCode::OK, Response::empty()))
+}
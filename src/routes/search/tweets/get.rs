@@ -0,0 +1,148 @@
+use crate::common::*;
+
+/// Relevance is a float, the cursor takes integers.
+const RANK_SCALE: f64 = 1_000_000.0;
+
+pub async fn get(request: &mut Request, database: Database) -> Result {
+    let session_id = optional_auth_token!(request, database);
+
+    let mut query = None;
+    let mut limit = 50;
+    let mut cursor = None;
+    let mut sort = search::Sort::Relevance;
+
+    for pair in request.uri().query().unwrap_or_default().split('&') {
+        match pair.split_once('=') {
+            Some(("q", value)) if value.len() <= search::MAX_QUERY_LENGTH => {
+                match search::decode_query_value(value)
+                    .as_deref()
+                    .and_then(search::parse)
+                {
+                    Some(value) => query = Some(value),
+                    None => {
+                        return Ok(Response::bad_request());
+                    }
+                }
+            }
+            Some(("limit", value)) => match value.parse::<i32>() {
+                Ok(value) if (0..=50).contains(&value) => limit = value,
+                _ => {
+                    return Ok(Response::bad_request());
+                }
+            },
+            Some(("cursor", value)) => cursor = Some(value),
+            Some(("sort", value)) => match value.parse::<search::Sort>() {
+                Ok(value) => sort = value,
+                Err(_) => {
+                    return Ok(Response::bad_request());
+                }
+            },
+            _ if pair.is_empty() => {}
+            _ => {
+                return Ok(Response::bad_request());
+            }
+        }
+    }
+
+    let query = match query {
+        Some(query) => query,
+        None => {
+            return Ok(Response::bad_request());
+        }
+    };
+
+    // without words to rank by, every match would be just as relevant
+    if query.text.is_empty() {
+        sort = search::Sort::Recency;
+    }
+
+    let cursor = match cursor {
+        Some(cursor) => match cursor::Cursor::decode_tagged(cursor, sort as u8) {
+            Some(cursor) => cursor,
+            None => {
+                return Ok(Response::bad_request());
+            }
+        },
+        None => cursor::Cursor::START,
+    };
+
+    // every filter is only part of the statement if it's used, since a generic plan for all of
+    // them couldn't use any index. Matching words, hashtags and authors drive the query through
+    // their own indexes, anything else through the one on the time
+    let mut filters = Vec::new();
+
+    if !query.text.is_empty() {
+        filters.push("tweets.text_search @@ search.query");
+    }
+
+    if query.from.is_some() {
+        filters.push("tweets.user_id = (SELECT id FROM users WHERE lower(username) = lower($2))");
+    }
+
+    // every hashtag has to be there, so the tweets have as many distinct ones of them as were asked for
+    if !query.hashtags.is_empty() {
+        filters.push("tweets.id IN (SELECT tweet_id FROM tweet_entities WHERE kind = 'hashtag' AND lower(value) = ANY (ARRAY(SELECT lower(hashtag) FROM unnest($3::TEXT[]) AS hashtag)) GROUP BY tweet_id HAVING count(DISTINCT lower(value)) = (SELECT count(DISTINCT lower(hashtag)) FROM unnest($3::TEXT[]) AS hashtag))");
+    }
+
+    if query.since.is_some() {
+        filters.push("tweets.time_created >= $4");
+    }
+
+    if query.until.is_some() {
+        filters.push("tweets.time_created < $5");
+    }
+
+    // the score is spelled out instead of taken from a subquery, so that ordering by the time
+    // can walk tweets_time_created_index
+    let score = match sort {
+        search::Sort::Recency => "tweets.time_created",
+        search::Sort::Relevance => "(ts_rank_cd(tweets.text_search, search.query) * $6)::BIGINT",
+    };
+
+    // the text is escaped before the matches are marked, so that the snippet can be shown as HTML
+    let result = sqlx::query(&format!("SELECT tweets.id, {score}, ts_headline('english', replace(replace(replace(tweets.text, '&', '&amp;'), '<', '&lt;'), '>', '&gt;'), search.query, 'StartSel=<mark>, StopSel=</mark>') FROM tweets JOIN users ON users.id = tweets.user_id CROSS JOIN LATERAL (SELECT websearch_to_tsquery('english', $1) AS query) AS search WHERE {filters} users.time_deactivated IS NULL AND users.time_suspended IS NULL AND ({score}, tweets.id) < ($7, $8) ORDER BY {score} DESC, tweets.id DESC LIMIT $9", score = score, filters = filters.iter().map(|filter| format!("{} AND ", filter)).collect::<String>()))
+        .bind(&query.text)
+        .bind(&query.from)
+        .bind(&query.hashtags)
+        .bind(query.since)
+        .bind(query.until)
+        .bind(RANK_SCALE)
+        .bind(cursor.time)
+        .bind(cursor.id)
+        .bind(limit + 1)
+        .fetch_all(&database)
+        .await;
+
+    let mut rows = unwrap_internal_error!(result)
+        .into_iter()
+        .map(|row| {
+            (
+                row.get_unchecked::<i64, _>(0),
+                row.get_unchecked::<i64, _>(1),
+                row.get_unchecked::<String, _>(2),
+            )
+        })
+        .collect::<Vec<(i64, i64, String)>>();
+
+    let next_cursor = cursor::next_tagged(&mut rows, limit, sort as u8, |(id, score, _)| {
+        cursor::Cursor {
+            time: *score,
+            id: *id,
+        }
+    });
+
+    let ids = rows.iter().map(|(id, ..)| *id).collect::<Vec<i64>>();
+
+    let mut found = unwrap_internal_error!(tweet::fetch(&database, &ids, session_id).await)
+        .into_iter()
+        .collect::<std::collections::HashMap<i64, Tweet>>();
+
+    let tweets = rows
+        .into_iter()
+        .filter_map(|(id, _, snippet)| Some(found.remove(&id)?.with_snippet(snippet)))
+        .collect::<Vec<Tweet>>();
+
+    let response = json!({ "tweets": tweets, "next_cursor": next_cursor });
+
+    Ok((StatusCode::OK, Response::success(response)))
+}
@@ -0,0 +1,115 @@
+use crate::common::*;
+
+/// How many levels of replies can be loaded at once.
+const MAX_DEPTH: i32 = 10;
+
+/// Below the first level, only that many replies are loaded per tweet. The rest can be paged
+/// through in the conversation of that reply.
+const MAX_NESTED_REPLIES: i32 = 10;
+
+/// Wide threads are cut off after that many replies, deeper levels first.
+const MAX_REPLIES: i64 = 500;
+
+pub async fn get(request: &mut Request, database: Database) -> Result {
+    let id = parse_path_var!(request, i64, 1);
+    let session_id = optional_auth_token!(request, database);
+
+    let mut limit = 50;
+    let mut cursor = cursor::Cursor::START;
+    let mut depth = 3;
+
+    for pair in request.uri().query().unwrap_or_default().split('&') {
+        match pair.split_once('=') {
+            Some(("limit", value)) => match value.parse::<i32>() {
+                Ok(value) if (0..=50).contains(&value) => limit = value,
+                _ => {
+                    return Ok(Response::bad_request());
+                }
+            },
+            Some(("cursor", value)) => match cursor::Cursor::decode(value) {
+                Some(value) => cursor = value,
+                None => {
+                    return Ok(Response::bad_request());
+                }
+            },
+            Some(("depth", value)) => match value.parse::<i32>() {
+                Ok(value) if (1..=MAX_DEPTH).contains(&value) => depth = value,
+                _ => {
+                    return Ok(Response::bad_request());
+                }
+            },
+            _ if pair.is_empty() => {}
+            _ => {
+                return Ok(Response::bad_request());
+            }
+        }
+    }
+
+    let result = tweet::fetch(&database, &[id], session_id).await;
+
+    let tweet = match unwrap_internal_error!(result).pop() {
+        Some((_, tweet)) => tweet,
+        None => {
+            return Ok(Response::not_found());
+        }
+    };
+
+    // every level is cut per tweet, replies of users that can't be seen are left out with all of
+    // their replies, but still count towards `reply_count`
+    let result = sqlx::query("WITH RECURSIVE thread (id, in_reply_to_tweet_id, time_created, depth) AS ((SELECT tweets.id, tweets.in_reply_to_tweet_id, tweets.time_created, 1 FROM tweets JOIN users ON users.id = tweets.user_id WHERE tweets.in_reply_to_tweet_id = $1 AND (tweets.time_created, tweets.id) < ($2, $3) AND users.time_deactivated IS NULL AND users.time_suspended IS NULL ORDER BY tweets.time_created DESC, tweets.id DESC LIMIT $4) UNION ALL SELECT reply.id, reply.in_reply_to_tweet_id, reply.time_created, thread.depth + 1 FROM thread CROSS JOIN LATERAL (SELECT tweets.id, tweets.in_reply_to_tweet_id, tweets.time_created FROM tweets JOIN users ON users.id = tweets.user_id WHERE tweets.in_reply_to_tweet_id = thread.id AND users.time_deactivated IS NULL AND users.time_suspended IS NULL ORDER BY tweets.time_created DESC, tweets.id DESC LIMIT $5) AS reply WHERE thread.depth < $6) SELECT id, in_reply_to_tweet_id, time_created, depth FROM thread LIMIT $7")
+        .bind(id)
+        .bind(cursor.time)
+        .bind(cursor.id)
+        .bind(limit + 1)
+        .bind(MAX_NESTED_REPLIES)
+        .bind(depth)
+        .bind(MAX_REPLIES + limit as i64 + 1)
+        .fetch_all(&database)
+        .await;
+
+    let mut rows = unwrap_internal_error!(result)
+        .into_iter()
+        .map(|row| {
+            (
+                row.get_unchecked::<i32, _>(3),
+                row.get_unchecked::<i64, _>(2),
+                row.get_unchecked::<i64, _>(0),
+                row.get_unchecked::<i64, _>(1),
+            )
+        })
+        .collect::<Vec<(i32, i64, i64, i64)>>();
+
+    // newest first on every level, like every other list
+    rows.sort_unstable_by(|a, b| (a.0, b.1, b.2).cmp(&(b.0, a.1, a.2)));
+
+    let mut first = rows
+        .iter()
+        .take_while(|(depth, ..)| *depth == 1)
+        .copied()
+        .collect::<Vec<(i32, i64, i64, i64)>>();
+
+    let next_cursor = cursor::next(&mut first, limit, |(_, time, id, _)| cursor::Cursor {
+        time: *time,
+        id: *id,
+    });
+
+    // the replies of the one reply too many are never reached
+    let mut replies = std::collections::HashMap::<i64, Vec<i64>>::new();
+    replies.insert(id, first.iter().map(|(_, _, id, _)| *id).collect());
+
+    for (_, _, id, in_reply_to_tweet_id) in rows.iter().skip_while(|(depth, ..)| *depth == 1) {
+        replies.entry(*in_reply_to_tweet_id).or_default().push(*id);
+    }
+
+    let ids = rows.iter().map(|(_, _, id, _)| *id).collect::<Vec<i64>>();
+
+    let mut found = unwrap_internal_error!(tweet::fetch(&database, &ids, session_id).await)
+        .into_iter()
+        .collect::<std::collections::HashMap<i64, Tweet>>();
+
+    let tweet = tweet::nest(id, tweet, &replies, &mut found, depth);
+
+    let response = json!({ "tweet": tweet, "next_cursor": next_cursor });
+
+    Ok((StatusCode::OK, Response::success(response)))
+}
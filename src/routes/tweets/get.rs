@@ -0,0 +1,70 @@
+use crate::common::*;
+
+/// The most tweets that can be looked up at once.
+const MAX_IDS: usize = 100;
+
+pub async fn get(request: &mut Request, database: Database) -> Result {
+    let session_id = optional_auth_token!(request, database);
+
+    if request.uri().path() != "/tweets" {
+        let id = parse_path_var!(request, i64);
+
+        let result = tweet::fetch(&database, &[id], session_id).await;
+
+        return match unwrap_internal_error!(result).pop() {
+            Some((_, tweet)) => Ok((StatusCode::OK, Response::success(tweet))),
+            None => Ok(Response::not_found()),
+        };
+    }
+
+    let mut ids = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+
+    for pair in request.uri().query().unwrap_or_default().split('&') {
+        match pair.split_once('=') {
+            // clients tend to encode the commas
+            Some(("ids", value)) => {
+                for id in value.replace("%2C", ",").replace("%2c", ",").split(',') {
+                    match id.parse::<i64>() {
+                        Ok(id) if seen.insert(id) => ids.push(id),
+                        Ok(_) => {}
+                        Err(_) => {
+                            return Ok(Response::bad_request());
+                        }
+                    }
+
+                    // there is no point in parsing the rest of a list that is too long anyway
+                    if ids.len() > MAX_IDS {
+                        return Ok(Response::bad_request());
+                    }
+                }
+            }
+            _ => {
+                return Ok(Response::bad_request());
+            }
+        }
+    }
+
+    if ids.is_empty() {
+        return Ok(Response::bad_request());
+    }
+
+    let mut found = unwrap_internal_error!(tweet::fetch(&database, &ids, session_id).await)
+        .into_iter()
+        .collect::<std::collections::HashMap<i64, Tweet>>();
+
+    // tweets that can't be seen are reported as missing, so that they can't be told apart
+    let mut tweets = Vec::with_capacity(found.len());
+    let mut missing = Vec::new();
+
+    for id in ids {
+        match found.remove(&id) {
+            Some(tweet) => tweets.push(tweet),
+            None => missing.push(id),
+        }
+    }
+
+    let response = json!({ "tweets": tweets, "missing": missing });
+
+    Ok((StatusCode::OK, Response::success(response)))
+}
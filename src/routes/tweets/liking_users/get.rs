@@ -0,0 +1,41 @@
+use crate::common::*;
+
+pub async fn get(request: &mut Request, database: Database) -> Result {
+    let tweet_id = parse_path_var!(request, i64, 1);
+
+    let result = sqlx::query("SELECT EXISTS (SELECT FROM tweets JOIN users ON users.id = tweets.user_id WHERE tweets.id = $1 AND users.time_deactivated IS NULL AND users.time_suspended IS NULL)")
+        .bind(tweet_id)
+        .fetch_one(&database)
+        .await;
+
+    if !unwrap_internal_error!(result).get_unchecked::<bool, _>(0) {
+        return Ok(Response::not_found());
+    }
+
+    let (limit, cursor) = cursor_pagination!(request);
+
+    // the like count still includes users that can't be seen
+    let result = sqlx::query(&format!("SELECT user_liked_tweets.time_created, {} FROM user_liked_tweets JOIN users ON users.id = user_liked_tweets.user_id WHERE user_liked_tweets.tweet_id = $1 AND (user_liked_tweets.time_created, user_liked_tweets.user_id) < ($2, $3) AND users.time_deactivated IS NULL AND users.time_suspended IS NULL ORDER BY user_liked_tweets.time_created DESC, user_liked_tweets.user_id DESC LIMIT $4", User::COLUMNS))
+        .bind(tweet_id)
+        .bind(cursor.time)
+        .bind(cursor.id)
+        .bind(limit + 1)
+        .fetch_all(&database)
+        .await;
+
+    let mut rows = unwrap_internal_error!(result);
+
+    let next_cursor = cursor::next(&mut rows, limit, |row| cursor::Cursor {
+        time: row.get_unchecked(0),
+        id: row.get_unchecked(1),
+    });
+
+    let users = rows
+        .into_iter()
+        .map(|row| User::from_row(&row, 1))
+        .collect::<Vec<User>>();
+
+    let response = json!({ "users": users, "next_cursor": next_cursor });
+
+    Ok((StatusCode::OK, Response::success(response)))
+}
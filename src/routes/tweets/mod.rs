@@ -0,0 +1,6 @@
+pub mod conversation;
+pub mod liking_users;
+
+mod get;
+
+pub use get::get;
@@ -0,0 +1,29 @@
+use crate::common::*;
+
+pub async fn get(request: &mut Request, database: Database) -> Result {
+    let username = match username::from_path(&parse_path_var!(request, String)) {
+        Some(username) => username,
+        None => {
+            return Ok(Response::bad_request());
+        }
+    };
+
+    // not username::is_invalid, since names from before the current rules can contain
+    // other characters and must still be found
+    if username.is_empty() || username.chars().count() > username::MAX_LENGTH {
+        return Ok(Response::bad_request());
+    }
+
+    // an old username still leads to its previous owner for as long as it's held back,
+    // so that links to a renamed user keep working
+    let result = sqlx::query(&format!("SELECT {} FROM users WHERE id = COALESCE((SELECT id FROM users WHERE lower(username) = lower($1)), (SELECT user_id FROM username_history WHERE lower(username) = lower($1) AND time_changed > extract(EPOCH FROM now()) - $2 ORDER BY time_changed DESC LIMIT 1)) AND time_deactivated IS NULL AND time_suspended IS NULL", User::COLUMNS))
+        .bind(&username)
+        .bind(*username::HOLD_PERIOD)
+        .fetch_optional(&database)
+        .await;
+
+    match unwrap_internal_error!(result) {
+        Some(row) => Ok((StatusCode::OK, Response::success(User::from_row(&row, 0)))),
+        None => Ok(Response::not_found()),
+    }
+}
@@ -0,0 +1,100 @@
+use crate::common::*;
+
+pub async fn delete(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    let session_id = check_auth_token!(request, database);
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        password: String,
+        #[serde(default)]
+        permanent: bool,
+    }
+
+    let body = body!(request, Body);
+
+    let result = sqlx::query(
+        "SELECT id, password_hash FROM users WHERE id = (SELECT user_id FROM sessions WHERE id = $1)",
+    )
+    .bind(session_id)
+    .fetch_optional(&database)
+    .await;
+
+    let row = match unwrap_internal_error!(result) {
+        Some(row) => row,
+        None => {
+            return Ok(Response::unauthorized());
+        }
+    };
+
+    let user_id = row.get_unchecked::<i64, _>(0);
+    let hash = row.get_unchecked::<Option<String>, _>(1);
+
+    // verified before the transaction, so that waiting for a hashing thread holds no connection
+    if !unwrap_internal_error!(password::verify_optional(body.password, hash.clone()).await) {
+        return Ok((StatusCode::FORBIDDEN, Response::error("Wrong password")));
+    }
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    // the password could have been changed since it was verified
+    let result = sqlx::query(
+        "SELECT FROM users WHERE id = $1 AND password_hash IS NOT DISTINCT FROM $2 FOR UPDATE",
+    )
+    .bind(user_id)
+    .bind(hash)
+    .fetch_optional(&mut transaction)
+    .await;
+
+    if unwrap_internal_error!(result).is_none() {
+        return Ok((
+            StatusCode::CONFLICT,
+            Response::error("Password was changed in the meantime"),
+        ));
+    }
+
+    if body.permanent || *ACCOUNT_GRACE_PERIOD == 0 {
+        // sessions, tweets and likes are removed by ON DELETE CASCADE,
+        // like counts of other users' tweets are fixed up by the update_like_count trigger
+        let result = sqlx::query("DELETE FROM users WHERE id = $1")
+            .bind(user_id)
+            .execute(&mut transaction)
+            .await;
+
+        unwrap_internal_error!(result);
+
+        let result = audit::record_event(&mut transaction, user_id, "user_deleted", request).await;
+
+        unwrap_internal_error!(result);
+
+        log::info!("Deleted user {}", user_id);
+    } else {
+        let result = sqlx::query(
+            "UPDATE users SET time_deactivated = extract(EPOCH FROM now()) WHERE id = $1",
+        )
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await;
+
+        unwrap_internal_error!(result);
+
+        let result = sqlx::query("DELETE FROM sessions WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut transaction)
+            .await;
+
+        unwrap_internal_error!(result);
+
+        let result =
+            audit::record_event(&mut transaction, user_id, "user_deactivated", request).await;
+
+        unwrap_internal_error!(result);
+
+        log::info!("Deactivated user {}", user_id);
+    }
+
+    unwrap_internal_error!(transaction.commit().await);
+
+    Ok((StatusCode::OK, Response::empty()))
+}
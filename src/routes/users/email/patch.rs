@@ -0,0 +1,61 @@
+use crate::common::*;
+
+pub async fn patch(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    let session_id = check_auth_token!(request, database);
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        email: String,
+        password: String,
+    }
+
+    let body = body!(request, Body);
+
+    if email::is_invalid(&body.email) {
+        return Ok(Response::bad_request());
+    }
+
+    let result = sqlx::query("SELECT users.id, username, password_hash FROM users JOIN sessions ON sessions.user_id = users.id WHERE sessions.id = $1")
+        .bind(session_id)
+        .fetch_optional(&database)
+        .await;
+
+    let row = match unwrap_internal_error!(result) {
+        Some(row) => row,
+        None => {
+            return Ok(Response::unauthorized());
+        }
+    };
+
+    let user_id = row.get_unchecked::<i64, _>(0);
+    let username = row.get_unchecked::<String, _>(1);
+    let hash = row.get_unchecked::<Option<String>, _>(2);
+
+    // a stolen session alone must not be enough to take over the account through a password reset
+    if !unwrap_internal_error!(password::verify_optional(body.password, hash).await) {
+        return Ok((StatusCode::FORBIDDEN, Response::error("Wrong password")));
+    }
+
+    let result = sqlx::query("SELECT EXISTS (SELECT FROM users WHERE lower(email) = lower($1) AND email_verified AND id <> $2)")
+        .bind(&body.email)
+        .bind(user_id)
+        .fetch_one(&database)
+        .await;
+
+    if unwrap_internal_error!(result).get_unchecked::<bool, _>(0) {
+        return Ok((
+            StatusCode::CONFLICT,
+            Response::error("Email already exists"),
+        ));
+    }
+
+    // the current address stays until the new one is verified, so that a typo can't lock the
+    // owner out of password resets
+    let result = email::send_verification(&database, user_id, &username, &body.email).await;
+
+    unwrap_internal_error!(result);
+
+    Ok((StatusCode::OK, Response::empty()))
+}
@@ -0,0 +1,30 @@
+use crate::common::*;
+
+pub async fn delete(request: &mut Request, database: Database) -> Result {
+    let user_id = parse_path_var!(request, i64);
+    let session_id = check_auth_token!(request, database);
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    let result = sqlx::query("DELETE FROM follows WHERE follower_id = (SELECT user_id FROM sessions WHERE id = $1) AND followee_id = $2 RETURNING follower_id")
+        .bind(session_id)
+        .bind(user_id)
+        .fetch_optional(&mut transaction)
+        .await;
+
+    let follower_id = match unwrap_internal_error!(result) {
+        Some(row) => row.get_unchecked::<i64, _>(0),
+        None => {
+            return Ok(Response::not_found());
+        }
+    };
+
+    let result = timeline::STORE
+        .unfollow(&mut transaction, follower_id, user_id)
+        .await;
+
+    unwrap_internal_error!(result);
+    unwrap_internal_error!(transaction.commit().await);
+
+    Ok((StatusCode::OK, Response::empty()))
+}
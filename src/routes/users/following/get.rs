@@ -0,0 +1,41 @@
+use crate::common::*;
+
+pub async fn get(request: &mut Request, database: Database) -> Result {
+    let user_id = parse_path_var!(request, i64, 1);
+
+    let result = sqlx::query("SELECT EXISTS (SELECT FROM users WHERE id = $1 AND time_deactivated IS NULL AND time_suspended IS NULL)")
+        .bind(user_id)
+        .fetch_one(&database)
+        .await;
+
+    if !unwrap_internal_error!(result).get_unchecked::<bool, _>(0) {
+        return Ok(Response::not_found());
+    }
+
+    let (limit, cursor) = cursor_pagination!(request);
+
+    // the latest follows first. `following_count` still includes the ones that can't be seen
+    let result = sqlx::query(&format!("SELECT follows.time_created, {} FROM follows JOIN users ON users.id = follows.followee_id WHERE follows.follower_id = $1 AND (follows.time_created, follows.followee_id) < ($2, $3) AND users.time_deactivated IS NULL AND users.time_suspended IS NULL ORDER BY follows.time_created DESC, follows.followee_id DESC LIMIT $4", User::COLUMNS))
+        .bind(user_id)
+        .bind(cursor.time)
+        .bind(cursor.id)
+        .bind(limit + 1)
+        .fetch_all(&database)
+        .await;
+
+    let mut rows = unwrap_internal_error!(result);
+
+    let next_cursor = cursor::next(&mut rows, limit, |row| cursor::Cursor {
+        time: row.get_unchecked(0),
+        id: row.get_unchecked(1),
+    });
+
+    let users = rows
+        .into_iter()
+        .map(|row| User::from_row(&row, 1))
+        .collect::<Vec<User>>();
+
+    let response = json!({ "users": users, "next_cursor": next_cursor });
+
+    Ok((StatusCode::OK, Response::success(response)))
+}
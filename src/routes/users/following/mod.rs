@@ -0,0 +1,7 @@
+mod delete;
+mod get;
+mod post;
+
+pub use delete::delete;
+pub use get::get;
+pub use post::post;
@@ -0,0 +1,82 @@
+use crate::common::*;
+
+pub async fn post(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    let session_id = check_auth_token!(request, database);
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        user_id: i64,
+    }
+
+    let body = body!(request, Body);
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    // both users are locked in the order of their ids up front. The foreign keys of the follow and
+    // the timeline backfill lock them as well, and doing that in different orders when two users
+    // follow each other at the same time deadlocks
+    let result = sqlx::query("SELECT FROM users WHERE id IN ((SELECT user_id FROM sessions WHERE id = $1), $2) ORDER BY id FOR UPDATE")
+        .bind(session_id)
+        .bind(body.user_id)
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+
+    // nobody can follow users that can't be seen
+    let result = sqlx::query("INSERT INTO follows (follower_id, followee_id) SELECT (SELECT user_id FROM sessions WHERE id = $1), id FROM users WHERE id = $2 AND time_deactivated IS NULL AND time_suspended IS NULL ON CONFLICT DO NOTHING RETURNING follower_id")
+        .bind(session_id)
+        .bind(body.user_id)
+        .fetch_optional(&mut transaction)
+        .await;
+
+    match result {
+        Ok(Some(row)) => {
+            let follower_id = row.get_unchecked::<i64, _>(0);
+
+            let result = timeline::STORE
+                .follow(&mut transaction, follower_id, body.user_id)
+                .await;
+
+            unwrap_internal_error!(result);
+            unwrap_internal_error!(transaction.commit().await);
+
+            Ok((StatusCode::CREATED, Response::empty()))
+        }
+        Ok(None) => {
+            let result = sqlx::query("SELECT EXISTS (SELECT FROM follows WHERE follower_id = (SELECT user_id FROM sessions WHERE id = $1) AND followee_id = $2)")
+                .bind(session_id)
+                .bind(body.user_id)
+                .fetch_one(&mut transaction)
+                .await;
+
+            if unwrap_internal_error!(result).get_unchecked::<bool, _>(0) {
+                Ok((
+                    StatusCode::CONFLICT,
+                    Response::error("User already followed"),
+                ))
+            } else {
+                Ok(Response::not_found())
+            }
+        }
+        Err(e) => {
+            if let Some(e) = e.as_database_error() {
+                if let Some(code) = e.code() {
+                    // CHECK VIOLATION
+                    if code == "23514" {
+                        return Ok((
+                            StatusCode::BAD_REQUEST,
+                            Response::error("Users can't follow themselves"),
+                        ));
+                    }
+                }
+            }
+
+            log::error!("{:?}", e);
+
+            Ok(Response::internal_server_error())
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use crate::common::*;
+
+pub async fn get(request: &mut Request, database: Database) -> Result {
+    let id = parse_path_var!(request, i64);
+
+    // deactivated and suspended users don't have public profiles
+    let result = sqlx::query(&format!("SELECT {} FROM users WHERE id = $1 AND time_deactivated IS NULL AND time_suspended IS NULL", User::COLUMNS))
+        .bind(id)
+        .fetch_optional(&database)
+        .await;
+
+    match unwrap_internal_error!(result) {
+        Some(row) => Ok((StatusCode::OK, Response::success(User::from_row(&row, 0)))),
+        None => Ok(Response::not_found()),
+    }
+}
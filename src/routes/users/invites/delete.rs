@@ -0,0 +1,38 @@
+use crate::common::*;
+
+pub async fn delete(request: &mut Request, database: Database) -> Result {
+    let id = parse_path_var!(request, i64);
+    let session_id = check_auth_token!(request, database);
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    // invites are only expired, so that users who redeemed them keep pointing at them. Rounding
+    // up would keep the invite usable for the rest of the second
+    let result = sqlx::query("UPDATE invites SET time_expires = floor(extract(EPOCH FROM now())) WHERE id = $1 AND creator_id = (SELECT user_id FROM sessions WHERE id = $2) AND time_expires > extract(EPOCH FROM now()) RETURNING creator_id")
+        .bind(id)
+        .bind(session_id)
+        .fetch_optional(&mut transaction)
+        .await;
+
+    let user_id = match unwrap_internal_error!(result) {
+        Some(row) => row.get_unchecked::<i64, _>(0),
+        None => {
+            return Ok(Response::not_found());
+        }
+    };
+
+    let details = format!("invite {}", id);
+
+    let event = audit::Event {
+        action: "invite_revoked",
+        actor_id: Some(user_id),
+        target_user_id: Some(user_id),
+        target_tweet_id: None,
+        details: Some(&details),
+    };
+
+    unwrap_internal_error!(audit::record(&mut transaction, event, request).await);
+    unwrap_internal_error!(transaction.commit().await);
+
+    Ok((StatusCode::OK, Response::empty()))
+}
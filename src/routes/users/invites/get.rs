@@ -0,0 +1,30 @@
+use crate::common::*;
+
+pub async fn get(request: &mut Request, database: Database) -> Result {
+    let session_id = check_auth_token!(request, database);
+
+    let (limit, offset) = pagination!(request);
+
+    let result = sqlx::query("SELECT id, code, max_uses, use_count, time_expires, time_created FROM invites WHERE creator_id = (SELECT user_id FROM sessions WHERE id = $1) ORDER BY time_created DESC, id DESC LIMIT $2 OFFSET $3")
+        .bind(session_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&database)
+        .await;
+
+    let response = unwrap_internal_error!(result)
+        .into_iter()
+        .map(|row| {
+            Invite::new(
+                row.get_unchecked(0),
+                row.get_unchecked(1),
+                row.get_unchecked(2),
+                row.get_unchecked(3),
+                row.get_unchecked(4),
+                row.get_unchecked(5),
+            )
+        })
+        .collect::<Vec<Invite>>();
+
+    Ok((StatusCode::OK, Response::success(response)))
+}
@@ -0,0 +1,96 @@
+use crate::common::*;
+
+pub async fn post(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    let session_id = check_auth_token!(request, database);
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        max_uses: Option<i32>,
+        expires_in: Option<i64>,
+    }
+
+    let body = body!(request, Body);
+
+    let max_uses = body.max_uses.unwrap_or(1);
+    let expires_in = body.expires_in.unwrap_or(registration::MAX_INVITE_LIFETIME);
+
+    if !(1..=registration::MAX_INVITE_USES).contains(&max_uses)
+        || !(60..=registration::MAX_INVITE_LIFETIME).contains(&expires_in)
+    {
+        return Ok(Response::bad_request());
+    }
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    // lock the row, so that concurrent requests can't exceed the quota together
+    let result = sqlx::query("SELECT id, role, coalesce(invite_quota, $2) FROM users WHERE id = (SELECT user_id FROM sessions WHERE id = $1) FOR UPDATE")
+        .bind(session_id)
+        .bind(*registration::INVITE_QUOTA)
+        .fetch_optional(&mut transaction)
+        .await;
+
+    let row = match unwrap_internal_error!(result) {
+        Some(row) => row,
+        None => {
+            return Ok(Response::unauthorized());
+        }
+    };
+
+    let user_id = row.get_unchecked::<i64, _>(0);
+
+    // admins can invite as many people as they like
+    if row.get_unchecked::<&str, _>(1) != auth::Role::Admin.as_str() {
+        let result = sqlx::query("SELECT coalesce(sum(CASE WHEN time_expires > extract(EPOCH FROM now()) THEN max_uses ELSE use_count END), 0)::BIGINT FROM invites WHERE creator_id = $1")
+            .bind(user_id)
+            .fetch_one(&mut transaction)
+            .await;
+
+        let used = unwrap_internal_error!(result).get_unchecked::<i64, _>(0);
+
+        if used + max_uses as i64 > row.get_unchecked::<i32, _>(2) as i64 {
+            return Ok((
+                StatusCode::FORBIDDEN,
+                Response::error("Invite quota exceeded"),
+            ));
+        }
+    }
+
+    let code = auth::generate_random_token();
+
+    let result = sqlx::query("INSERT INTO invites (code, creator_id, max_uses, time_expires) VALUES ($1, $2, $3, extract(EPOCH FROM now()) + $4) RETURNING id, time_expires, time_created")
+        .bind(&code)
+        .bind(user_id)
+        .bind(max_uses)
+        .bind(expires_in)
+        .fetch_one(&mut transaction)
+        .await;
+
+    let row = unwrap_internal_error!(result);
+    let id = row.get_unchecked::<i64, _>(0);
+
+    let details = format!("invite {}", id);
+
+    let event = audit::Event {
+        action: "invite_created",
+        actor_id: Some(user_id),
+        target_user_id: Some(user_id),
+        target_tweet_id: None,
+        details: Some(&details),
+    };
+
+    unwrap_internal_error!(audit::record(&mut transaction, event, request).await);
+    unwrap_internal_error!(transaction.commit().await);
+
+    let response = Invite::new(
+        id,
+        code,
+        max_uses,
+        0,
+        row.get_unchecked(1),
+        row.get_unchecked(2),
+    );
+
+    Ok((StatusCode::CREATED, Response::success(response)))
+}
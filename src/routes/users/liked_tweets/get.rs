@@ -0,0 +1,53 @@
+use crate::common::*;
+
+pub async fn get(request: &mut Request, database: Database) -> Result {
+    // likes are as public as the tweets of the user
+    let user_id = if request.uri().path() == "/users/@me/liked_tweets" {
+        let session_id = check_auth_token!(request, database);
+
+        sqlx::query("SELECT user_id FROM sessions WHERE id = $1")
+            .bind(session_id)
+            .fetch_optional(&database)
+            .await
+    } else {
+        let user_id = parse_path_var!(request, i64, 1);
+
+        sqlx::query("SELECT id FROM users WHERE id = $1 AND time_deactivated IS NULL AND time_suspended IS NULL")
+            .bind(user_id)
+            .fetch_optional(&database)
+            .await
+    };
+
+    let user_id = match unwrap_internal_error!(user_id) {
+        Some(row) => row.get_unchecked::<i64, _>(0),
+        None => {
+            return Ok(Response::not_found());
+        }
+    };
+
+    let (limit, cursor) = cursor_pagination!(request);
+
+    let result = sqlx::query(&format!("SELECT user_liked_tweets.time_created, {}, {} FROM user_liked_tweets JOIN tweets ON tweets.id = user_liked_tweets.tweet_id JOIN users ON users.id = tweets.user_id WHERE user_liked_tweets.user_id = $1 AND (user_liked_tweets.time_created, user_liked_tweets.tweet_id) < ($2, $3) AND users.time_deactivated IS NULL AND users.time_suspended IS NULL ORDER BY user_liked_tweets.time_created DESC, user_liked_tweets.tweet_id DESC LIMIT $4", Tweet::COLUMNS, User::COLUMNS))
+        .bind(user_id)
+        .bind(cursor.time)
+        .bind(cursor.id)
+        .bind(limit + 1)
+        .fetch_all(&database)
+        .await;
+
+    let mut rows = unwrap_internal_error!(result);
+
+    let next_cursor = cursor::next(&mut rows, limit, |row| cursor::Cursor {
+        time: row.get_unchecked(0),
+        id: row.get_unchecked(1),
+    });
+
+    let tweets = rows
+        .into_iter()
+        .map(|row| Tweet::from_row(&row, 1).with_author(User::from_row(&row, 12)))
+        .collect::<Vec<Tweet>>();
+
+    let response = json!({ "tweets": tweets, "next_cursor": next_cursor });
+
+    Ok((StatusCode::OK, Response::success(response)))
+}
@@ -3,7 +3,7 @@ use crate::common::*;
 pub async fn post(request: &mut Request, database: Database) -> Result {
     check_content_type!(request);
 
-    let session_id = check_auth_token!(request);
+    let session_id = check_auth_token!(request, database);
 
     #[derive(serde::Deserialize)]
     struct Body {
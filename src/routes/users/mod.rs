@@ -1,7 +1,24 @@
+pub mod by;
+pub mod email;
+pub mod followers;
+pub mod following;
+pub mod invites;
 pub mod liked_tweets;
+pub mod password;
+pub mod retweets;
+pub mod security_events;
 pub mod sessions;
+pub mod timeline;
 pub mod tweets;
+pub mod two_factor;
+pub mod username;
 
+mod delete;
+mod get;
+mod patch;
 mod post;
 
+pub use delete::delete;
+pub use get::get;
+pub use patch::patch;
 pub use post::post;
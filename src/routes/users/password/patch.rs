@@ -0,0 +1,87 @@
+use crate::common::*;
+
+pub async fn patch(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    let session_id = check_auth_token!(request, database);
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        current_password: Option<String>,
+        new_password: String,
+    }
+
+    let body = body!(request, Body);
+
+    if Credentials::is_invalid_password(&body.new_password) {
+        return Ok(Response::bad_request());
+    }
+
+    let result = sqlx::query("SELECT id, password_hash FROM users WHERE id = (SELECT user_id FROM sessions WHERE id = $1)")
+        .bind(session_id)
+        .fetch_optional(&database)
+        .await;
+
+    let row = match unwrap_internal_error!(result) {
+        Some(row) => row,
+        None => {
+            return Ok(Response::unauthorized());
+        }
+    };
+
+    let user_id = row.get_unchecked::<i64, _>(0);
+    let old_hash = row.get_unchecked::<Option<String>, _>(1);
+
+    // accounts created through OpenID Connect have no password, so they can set the first one
+    // without, otherwise they could never delete the account or change the email address
+    if old_hash.is_some() {
+        let current_password = match body.current_password {
+            Some(current_password) => current_password,
+            None => {
+                return Ok(Response::bad_request());
+            }
+        };
+
+        if !unwrap_internal_error!(
+            password::verify_optional(current_password, old_hash.clone()).await
+        ) {
+            return Ok((StatusCode::FORBIDDEN, Response::error("Wrong password")));
+        }
+    }
+
+    // the slow hashing happens before the transaction, so that it holds no connection
+    let hash = unwrap_internal_error!(password::hash(body.new_password).await);
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    // only if the password is still the verified one, so that concurrent changes can't interleave
+    let result = sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2 AND password_hash IS NOT DISTINCT FROM $3")
+        .bind(hash)
+        .bind(user_id)
+        .bind(old_hash)
+        .execute(&mut transaction)
+        .await;
+
+    if unwrap_internal_error!(result).rows_affected() == 0 {
+        return Ok((
+            StatusCode::CONFLICT,
+            Response::error("Password was changed in the meantime"),
+        ));
+    }
+
+    // whoever knew the old password might still be logged in somewhere else
+    let result = sqlx::query("DELETE FROM sessions WHERE user_id = $1 AND id <> $2")
+        .bind(user_id)
+        .bind(session_id)
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+
+    let result = audit::record_event(&mut transaction, user_id, "password_changed", request).await;
+
+    unwrap_internal_error!(result);
+    unwrap_internal_error!(transaction.commit().await);
+
+    Ok((StatusCode::OK, Response::empty()))
+}
@@ -0,0 +1,49 @@
+use crate::common::*;
+
+pub async fn patch(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    let session_id = check_auth_token!(request, database);
+
+    // missing fields stay as they are, empty ones are cleared
+    #[derive(serde::Deserialize)]
+    struct Body {
+        display_name: Option<String>,
+        bio: Option<String>,
+        location: Option<String>,
+        website: Option<String>,
+        avatar: Option<String>,
+    }
+
+    let body = body!(request, Body);
+
+    macro_rules! is_invalid {
+        ($field:ident, $check:path) => {
+            matches!(body.$field.as_deref(), Some(val) if !val.is_empty() && $check(val))
+        };
+    }
+
+    if is_invalid!(display_name, profile::is_invalid_display_name)
+        || is_invalid!(bio, profile::is_invalid_bio)
+        || is_invalid!(location, profile::is_invalid_location)
+        || is_invalid!(website, profile::is_invalid_website)
+        || is_invalid!(avatar, profile::is_invalid_avatar)
+    {
+        return Ok(Response::bad_request());
+    }
+
+    let result = sqlx::query(&format!("UPDATE users SET display_name = CASE WHEN $1 IS NULL THEN display_name ELSE NULLIF($1, '') END, bio = CASE WHEN $2 IS NULL THEN bio ELSE NULLIF($2, '') END, location = CASE WHEN $3 IS NULL THEN location ELSE NULLIF($3, '') END, website = CASE WHEN $4 IS NULL THEN website ELSE NULLIF($4, '') END, avatar = CASE WHEN $5 IS NULL THEN avatar ELSE NULLIF($5, '') END WHERE id = (SELECT user_id FROM sessions WHERE id = $6) RETURNING {}", User::COLUMNS))
+        .bind(&body.display_name)
+        .bind(&body.bio)
+        .bind(&body.location)
+        .bind(&body.website)
+        .bind(&body.avatar)
+        .bind(session_id)
+        .fetch_optional(&database)
+        .await;
+
+    match unwrap_internal_error!(result) {
+        Some(row) => Ok((StatusCode::OK, Response::success(User::from_row(&row, 0)))),
+        None => Ok(Response::unauthorized()),
+    }
+}
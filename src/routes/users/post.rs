@@ -1,28 +1,132 @@
 use crate::common::*;
-use argon2::password_hash::rand_core::OsRng;
-use argon2::password_hash::SaltString;
-use argon2::PasswordHasher;
 
 pub async fn post(request: &mut Request, database: Database) -> Result {
     check_content_type!(request);
 
-    let credentials = body!(request, Credentials);
+    #[derive(serde::Deserialize)]
+    struct Body {
+        #[serde(flatten)]
+        credentials: Credentials,
+        email: Option<String>,
+        invite: Option<String>,
+        challenge: Option<String>,
+        solution: Option<String>,
+    }
+
+    let Body {
+        mut credentials,
+        email,
+        invite,
+        challenge,
+        solution,
+    } = body!(request, Body);
+
+    match *registration::MODE {
+        registration::Mode::Open => {}
+        registration::Mode::InviteOnly if invite.is_some() => {}
+        registration::Mode::InviteOnly => {
+            return Ok((
+                StatusCode::FORBIDDEN,
+                Response::error("An invite is required"),
+            ));
+        }
+        registration::Mode::Closed => {
+            return Ok((
+                StatusCode::FORBIDDEN,
+                Response::error("Registration is closed"),
+            ));
+        }
+    }
 
-    if credentials.is_invalid() {
+    credentials.username = username::normalize(&credentials.username);
+
+    if credentials.is_invalid()
+        || username::is_invalid(&credentials.username)
+        || matches!(email.as_deref(), Some(email) if email::is_invalid(email))
+    {
         return Ok(Response::bad_request());
     }
 
+    if username::is_reserved(&credentials.username) {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Response::error("Username is reserved"),
+        ));
+    }
+
+    let client = client!(request);
+
+    if !unwrap_internal_error!(
+        proof_of_work::verify(
+            &database,
+            client.address,
+            challenge.as_deref(),
+            solution.as_deref()
+        )
+        .await
+    ) {
+        return Ok((StatusCode::FORBIDDEN, Response::error("Invalid challenge")));
+    }
+
+    // old usernames of other users are taken as well, as long as they are held back
+    if unwrap_internal_error!(username::is_held(&database, &credentials.username, None).await) {
+        return Ok((
+            StatusCode::CONFLICT,
+            Response::error("Username already exists"),
+        ));
+    }
+
+    // in production i would prefer to either avoid password-based auth at all or to
+    // outsource it to some cloud service (like auth0 or Cognito).
+    // it's hashed before the transaction, so that waiting for a hashing thread holds no connection
+    let hash = unwrap_internal_error!(password::hash(credentials.password).await);
+
     // if an in-progress transaction goes out of scope, it will rollback automatically
-    let transaction = unwrap_internal_error!(database.begin().await);
+    let mut transaction = unwrap_internal_error!(database.begin().await);
 
-    let result =
-        sqlx::query("INSERT INTO users (username) VALUES ($1) ON CONFLICT DO NOTHING RETURNING id")
-            .bind(&credentials.username)
-            .fetch_optional(&database)
-            .await;
+    // the use is given back if the sign up fails. Invites must be valid even if registration
+    // is open, since the invite tree would be wrong otherwise
+    let invite = match &invite {
+        Some(code) => {
+            match unwrap_internal_error!(registration::redeem_invite(&mut transaction, code).await)
+            {
+                Some(invite) => Some(invite),
+                None => {
+                    return Ok((StatusCode::FORBIDDEN, Response::error("Invalid invite")));
+                }
+            }
+        }
+        None => None,
+    };
+
+    // unverified addresses can be claimed by anyone, so they don't count
+    if let Some(email) = &email {
+        let result = sqlx::query(
+            "SELECT EXISTS (SELECT FROM users WHERE lower(email) = lower($1) AND email_verified)",
+        )
+        .bind(email)
+        .fetch_one(&mut transaction)
+        .await;
 
-    let user_id = match unwrap_internal_error!(result) {
-        Some(row) => row.get_unchecked::<i64, _>(0),
+        if unwrap_internal_error!(result).get_unchecked::<bool, _>(0) {
+            return Ok((
+                StatusCode::CONFLICT,
+                Response::error("Email already exists"),
+            ));
+        }
+    }
+
+    let result = sqlx::query(&format!("INSERT INTO users (username, password_hash, invite_id, invited_by) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING RETURNING {}", User::COLUMNS))
+        .bind(&credentials.username)
+        .bind(hash)
+        .bind(invite.map(|(invite_id, _)| invite_id))
+        .bind(invite.map(|(_, inviter_id)| inviter_id))
+        .fetch_optional(&mut transaction)
+        .await;
+
+    // unverified addresses aren't stored in users, so only the username can be taken
+    let row = match unwrap_internal_error!(result) {
+        Some(row) => row,
         None => {
             return Ok((
                 StatusCode::CONFLICT,
@@ -31,25 +135,31 @@ pub async fn post(request: &mut Request, database: Database) -> Result {
         }
     };
 
-    let salt = SaltString::generate(&mut OsRng);
-    let password = credentials.password.as_bytes();
+    let user_id = row.get_unchecked::<i64, _>(0);
 
-    // in production i would prefer to either avoid password-based auth at all or to
-    // outsource it to some cloud service (like auth0 or Cognito)
-    // https://cheatsheetseries.owasp.org/cheatsheets/Password_Storage_Cheat_Sheet.html#argon2id
-    let hash = unwrap_internal_error!(ARGON2.hash_password(password, &salt)).to_string();
+    let details = invite.map(|(invite_id, _)| format!("invite {}", invite_id));
 
-    let result = sqlx::query("UPDATE users SET password_hash = $1 WHERE id = $2")
-        .bind(hash)
-        .bind(user_id)
-        .execute(&database)
-        .await;
+    let event = audit::Event {
+        action: "user_created",
+        actor_id: Some(user_id),
+        target_user_id: Some(user_id),
+        target_tweet_id: None,
+        details: details.as_deref(),
+    };
 
-    unwrap_internal_error!(result);
+    unwrap_internal_error!(audit::record(&mut transaction, event, request).await);
+    unwrap_internal_error!(transaction.commit().await);
 
-    tokio::spawn(transaction.commit());
+    // the address is only stored once it's verified, until then the verification carries it
+    if let Some(email) = &email {
+        let result =
+            email::send_verification(&database, user_id, &credentials.username, email).await;
 
-    let response = json!({ "id": user_id, "username": credentials.username });
+        unwrap_internal_error!(result);
+    }
 
-    Ok((StatusCode::CREATED, Response::success(response)))
+    Ok((
+        StatusCode::CREATED,
+        Response::success(User::from_row(&row, 0)),
+    ))
 }
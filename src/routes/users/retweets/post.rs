@@ -0,0 +1,58 @@
+use crate::common::*;
+
+pub async fn post(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    let session_id = check_auth_token!(request, database);
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        tweet_id: i64,
+    }
+
+    let body = body!(request, Body);
+
+    // nobody can retweet tweets that can't be seen
+    let result = sqlx::query("INSERT INTO retweets (user_id, tweet_id) SELECT (SELECT user_id FROM sessions WHERE id = $1), tweets.id FROM tweets JOIN users ON users.id = tweets.user_id WHERE tweets.id = $2 AND users.time_deactivated IS NULL AND users.time_suspended IS NULL ON CONFLICT DO NOTHING")
+        .bind(session_id)
+        .bind(body.tweet_id)
+        .execute(&database)
+        .await;
+
+    match result {
+        Ok(result) => {
+            if result.rows_affected() == 1 {
+                return Ok((StatusCode::CREATED, Response::empty()));
+            }
+        }
+        Err(e) => {
+            if let Some(e) = e.as_database_error() {
+                if let Some(code) = e.code() {
+                    // FOREIGN KEY VIOLATION, the tweet was deleted in the meantime
+                    if code == "23503" {
+                        return Ok(Response::not_found());
+                    }
+                }
+            }
+
+            log::error!("{:?}", e);
+
+            return Ok(Response::internal_server_error());
+        }
+    }
+
+    let result = sqlx::query("SELECT EXISTS (SELECT FROM retweets WHERE user_id = (SELECT user_id FROM sessions WHERE id = $1) AND tweet_id = $2)")
+        .bind(session_id)
+        .bind(body.tweet_id)
+        .fetch_one(&database)
+        .await;
+
+    if unwrap_internal_error!(result).get_unchecked::<bool, _>(0) {
+        Ok((
+            StatusCode::CONFLICT,
+            Response::error("Tweet already retweeted"),
+        ))
+    } else {
+        Ok(Response::not_found())
+    }
+}
@@ -0,0 +1,30 @@
+use crate::common::*;
+
+pub async fn get(request: &mut Request, database: Database) -> Result {
+    let session_id = check_auth_token!(request, database);
+
+    let (limit, offset) = pagination!(request);
+
+    // what staff did to the account stays in the admin trail, the user must not see their addresses
+    let result = sqlx::query("SELECT id, action, host(ip_address), user_agent, time_created FROM audit_events WHERE target_user_id = (SELECT user_id FROM sessions WHERE id = $1) AND (actor_id IS NULL OR actor_id = target_user_id) ORDER BY time_created DESC, id DESC LIMIT $2 OFFSET $3")
+        .bind(session_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&database)
+        .await;
+
+    let response = unwrap_internal_error!(result)
+        .into_iter()
+        .map(|row| {
+            SecurityEvent::new(
+                row.get_unchecked(0),
+                row.get_unchecked(1),
+                row.get_unchecked(2),
+                row.get_unchecked(3),
+                row.get_unchecked(4),
+            )
+        })
+        .collect::<Vec<SecurityEvent>>();
+
+    Ok((StatusCode::OK, Response::success(response)))
+}
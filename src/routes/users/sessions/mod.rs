@@ -1,3 +1,6 @@
+pub mod oidc;
+pub mod two_factor;
+
 mod post;
 
 pub use post::post;
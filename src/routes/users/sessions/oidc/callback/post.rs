@@ -0,0 +1,104 @@
+use crate::common::*;
+
+pub async fn post(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    let config = match &*oidc::CONFIG {
+        Some(config) => config,
+        None => {
+            return Ok(Response::not_found());
+        }
+    };
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        code: String,
+        state: String,
+    }
+
+    let body = body!(request, Body);
+
+    // every login can only be completed once
+    let result = sqlx::query("DELETE FROM oidc_logins WHERE state_hash = $1 AND time_created > extract(EPOCH FROM now()) - $2 RETURNING code_verifier, nonce, extract(EPOCH FROM now())::BIGINT, user_agent, host(ip_address), tls_version, alpn_protocol")
+        .bind(auth::hash_random_token(&body.state))
+        .bind(oidc::LOGIN_LIFETIME)
+        .fetch_optional(&database)
+        .await;
+
+    let row = match unwrap_internal_error!(result) {
+        Some(row) => row,
+        None => {
+            return Ok(Response::unauthorized());
+        }
+    };
+
+    // the login was started by another client, which is what a login CSRF looks like. This is
+    // a defense of its own, so unlike the session binding it can't be turned off or down
+    let mismatches = auth::binding_mismatches(
+        request,
+        row.get_unchecked(3),
+        row.get_unchecked(4),
+        row.get_unchecked(5),
+        row.get_unchecked(6),
+    );
+
+    if !mismatches.is_empty() {
+        log::warn!(
+            "OpenID Connect login completed by another client: {} mismatch",
+            mismatches.join(", ")
+        );
+
+        return Ok(Response::unauthorized());
+    }
+
+    let code_verifier = row.get_unchecked::<&str, _>(0);
+    let nonce = row.get_unchecked::<&str, _>(1);
+
+    let claims = match oidc::exchange_code(config, &body.code, code_verifier).await {
+        Ok(id_token) => oidc::decode_id_token(config, &id_token, nonce, row.get_unchecked(2)),
+        Err(e) => Err(e),
+    };
+
+    let claims = match claims {
+        Ok(claims) => claims,
+        Err(oidc::Error::Request(e)) => {
+            log::error!("{}", e);
+            return Ok(Response::bad_gateway());
+        }
+        Err(e) => {
+            log::warn!("{}", e);
+            return Ok(Response::unauthorized());
+        }
+    };
+
+    let user_id =
+        match unwrap_internal_error!(oidc::find_or_create_user(&database, config, &claims).await) {
+            Some(user_id) => user_id,
+            None => {
+                return Ok(Response::unauthorized());
+            }
+        };
+
+    if unwrap_internal_error!(auth::is_suspended(&database, user_id).await) {
+        return Ok((
+            StatusCode::FORBIDDEN,
+            Response::error("Account is suspended"),
+        ));
+    }
+
+    let result = sqlx::query("SELECT totp_enabled FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&database)
+        .await;
+
+    // the provider is trusted with the password, but not with the second factor
+    if unwrap_internal_error!(result).get_unchecked::<bool, _>(0) {
+        let token = unwrap_internal_error!(two_factor::create_challenge(&database, user_id).await);
+
+        return Ok((StatusCode::ACCEPTED, Response::success(token)));
+    }
+
+    let token = unwrap_internal_error!(auth::create_session(&database, user_id, request).await);
+
+    Ok((StatusCode::CREATED, Response::success(token)))
+}
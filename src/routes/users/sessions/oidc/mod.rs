@@ -0,0 +1,5 @@
+pub mod callback;
+
+mod post;
+
+pub use post::post;
@@ -0,0 +1,44 @@
+use crate::common::*;
+
+pub async fn post(request: &mut Request, database: Database) -> Result {
+    let config = match &*oidc::CONFIG {
+        Some(config) => config,
+        None => {
+            return Ok(Response::not_found());
+        }
+    };
+
+    let state = auth::generate_random_token();
+    let nonce = auth::generate_random_token();
+    let code_verifier = auth::generate_random_token();
+
+    let url = match oidc::authorization_url(config, &state, &nonce, &code_verifier).await {
+        Ok(url) => url,
+        Err(e) => {
+            log::error!("{}", e);
+            return Ok(Response::bad_gateway());
+        }
+    };
+
+    let client = client!(request);
+
+    // the state comes back with the callback, the rest must never leave the server. The
+    // client is remembered, so that nobody can slip their own login into someone else's
+    let result = sqlx::query("INSERT INTO oidc_logins (state_hash, code_verifier, nonce, user_agent, ip_address, tls_version, alpn_protocol) VALUES ($1, $2, $3, $4, $5::inet, $6, $7)")
+        .bind(auth::hash_random_token(&state))
+        .bind(code_verifier)
+        .bind(nonce)
+        .bind(user_agent!(request))
+        .bind(client.address.to_string())
+        .bind(client.tls_version)
+        .bind(&client.alpn_protocol)
+        .execute(&database)
+        .await;
+
+    unwrap_internal_error!(result);
+
+    Ok((
+        StatusCode::OK,
+        Response::success(json!({ "authorization_url": url })),
+    ))
+}
@@ -1,44 +1,163 @@
 use crate::common::*;
-use argon2::{PasswordHash, PasswordVerifier};
 
 pub async fn post(request: &mut Request, database: Database) -> Result {
     check_content_type!(request);
 
-    let credentials = body!(request, Credentials);
+    #[derive(serde::Deserialize)]
+    struct Body {
+        #[serde(flatten)]
+        credentials: Credentials,
+        challenge: Option<String>,
+        solution: Option<String>,
+    }
+
+    let Body {
+        mut credentials,
+        challenge,
+        solution,
+    } = body!(request, Body);
+
+    // the same normalization as on sign up, so that "Ｈｅｌｌｏ" logs in as "hello"
+    credentials.username = username::normalize(&credentials.username);
 
     if credentials.is_invalid() {
         return Ok(Response::bad_request());
     }
 
-    let result = sqlx::query("SELECT id, password_hash FROM users WHERE username = $1")
-        .bind(credentials.username)
+    let client = client!(request);
+
+    if unwrap_internal_error!(
+        throttle::is_locked(&database, &credentials.username, client.address).await
+    ) {
+        return Ok((
+            StatusCode::TOO_MANY_REQUESTS,
+            Response::error("Too many failed login attempts"),
+        ));
+    }
+
+    if !unwrap_internal_error!(
+        proof_of_work::verify(
+            &database,
+            client.address,
+            challenge.as_deref(),
+            solution.as_deref()
+        )
+        .await
+    ) {
+        return Ok((StatusCode::FORBIDDEN, Response::error("Invalid challenge")));
+    }
+
+    // accounts past their grace period are about to be purged, so they can't be restored anymore
+    let result = sqlx::query("SELECT id, password_hash, time_deactivated IS NOT NULL, totp_enabled, time_suspended IS NOT NULL FROM users WHERE lower(username) = lower($1) AND (time_deactivated IS NULL OR time_deactivated > extract(EPOCH FROM now()) - $2)")
+        .bind(&credentials.username)
+        .bind(*ACCOUNT_GRACE_PERIOD)
         .fetch_optional(&database)
         .await;
 
-    let row = match unwrap_internal_error!(result) {
-        Some(row) => row,
-        None => {
-            return Ok(Response::not_found());
+    let row = unwrap_internal_error!(result);
+
+    // unknown usernames must be indistinguishable from wrong passwords, timing included
+    let hash = row
+        .as_ref()
+        .and_then(|row| row.get_unchecked::<Option<String>, _>(1));
+
+    // the hash that was verified, so that the upgrade doesn't undo a concurrent password change
+    let outdated_hash = hash.clone().filter(|hash| password::needs_rehash(hash));
+    let valid =
+        unwrap_internal_error!(password::verify_optional(credentials.password.clone(), hash).await);
+
+    let row = match row {
+        Some(row) if valid => row,
+        row => {
+            let event = audit::Event {
+                action: "login_failed",
+                actor_id: None,
+                target_user_id: row.as_ref().map(|row| row.get_unchecked(0)),
+                target_tweet_id: None,
+                details: None,
+            };
+
+            unwrap_internal_error!(audit::record(&database, event, request).await);
+
+            let result =
+                throttle::record_failure(&database, &credentials.username, client.address).await;
+
+            if unwrap_internal_error!(result) {
+                if let Some(row) = row {
+                    let event = audit::Event {
+                        action: "account_locked",
+                        actor_id: None,
+                        target_user_id: Some(row.get_unchecked(0)),
+                        target_tweet_id: None,
+                        details: None,
+                    };
+
+                    unwrap_internal_error!(audit::record(&database, event, request).await);
+                }
+            }
+
+            return Ok(Response::unauthorized());
         }
     };
 
-    let hash = row.get_unchecked::<String, _>(1);
-    let hash = unwrap_internal_error!(PasswordHash::new(&hash));
+    let user_id = row.get_unchecked::<i64, _>(0);
 
-    if let Err(_) = ARGON2.verify_password(credentials.password.as_bytes(), &hash) {
-        return Ok(Response::not_found());
+    // only after the password check, so that it doesn't tell anyone else who is suspended
+    if row.get_unchecked::<bool, _>(4) {
+        return Ok((
+            StatusCode::FORBIDDEN,
+            Response::error("Account is suspended"),
+        ));
     }
 
-    let result = sqlx::query("INSERT INTO sessions (user_id) VALUES ($1) RETURNING id")
-        .bind(row.get_unchecked::<i64, _>(0))
-        .fetch_one(&database)
-        .await;
+    // the plaintext password is only available right now, so this is our only chance
+    // to upgrade a hash made with weaker parameters
+    if let Some(outdated_hash) = outdated_hash {
+        let database = database.clone();
+        let password = credentials.password;
+
+        tokio::spawn(async move {
+            let hash = match password::hash(password).await {
+                Ok(hash) => hash,
+                Err(e) => {
+                    log::error!("{:?}", e);
+                    return;
+                }
+            };
+
+            let result = sqlx::query(
+                "UPDATE users SET password_hash = $1 WHERE id = $2 AND password_hash = $3",
+            )
+            .bind(hash)
+            .bind(user_id)
+            .bind(outdated_hash)
+            .execute(&database)
+            .await;
+
+            match result {
+                Ok(result) if result.rows_affected() == 0 => {
+                    log::info!(
+                        "Skipped upgrading the changed password hash of user {}",
+                        user_id
+                    )
+                }
+                Ok(_) => log::info!("Upgraded the password hash of user {}", user_id),
+                Err(e) => log::error!("{:?}", e),
+            }
+        });
+    }
+
+    // the second factor is checked by sessions::two_factor::post
+    if row.get_unchecked::<bool, _>(3) {
+        let token = unwrap_internal_error!(two_factor::create_challenge(&database, user_id).await);
+
+        return Ok((StatusCode::ACCEPTED, Response::success(token)));
+    }
 
-    let session_id = unwrap_internal_error!(result)
-        .get_unchecked::<i64, _>(0)
-        .to_le_bytes();
+    // with two factors, the failures are only forgotten once the second one passes
+    unwrap_internal_error!(throttle::record_success(&database, &credentials.username).await);
 
-    let token = auth::create_token(&session_id);
+    let token = unwrap_internal_error!(auth::create_session(&database, user_id, request).await);
 
     Ok((StatusCode::CREATED, Response::success(token)))
 }
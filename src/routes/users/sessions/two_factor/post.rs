@@ -0,0 +1,120 @@
+use crate::common::*;
+
+pub async fn post(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        challenge: String,
+        code: String,
+    }
+
+    let body = body!(request, Body);
+
+    let challenge_id = match auth::decode_challenge_token(body.challenge.as_bytes()) {
+        Some(challenge_id) => challenge_id,
+        None => {
+            return Ok(Response::unauthorized());
+        }
+    };
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    // the challenge is claimed before the code is checked, so that of two requests with the
+    // same challenge only one gets to use up a code, and the other one waits and fails
+    let result = sqlx::query("SELECT two_factor_challenges.user_id, users.username FROM two_factor_challenges JOIN users ON users.id = two_factor_challenges.user_id WHERE two_factor_challenges.id = $1 AND two_factor_challenges.time_created > extract(EPOCH FROM now()) - $2 FOR UPDATE OF two_factor_challenges")
+        .bind(challenge_id)
+        .bind(two_factor::CHALLENGE_LIFETIME)
+        .fetch_optional(&mut transaction)
+        .await;
+
+    let (user_id, username) = match unwrap_internal_error!(result) {
+        Some(row) => (
+            row.get_unchecked::<i64, _>(0),
+            row.get_unchecked::<String, _>(1),
+        ),
+        None => {
+            return Ok(Response::unauthorized());
+        }
+    };
+
+    let client = client!(request);
+
+    // wrong codes lock out the username just like wrong passwords, otherwise anyone who knows
+    // the password could keep guessing with fresh challenges
+    if unwrap_internal_error!(throttle::is_locked(&database, &username, client.address).await) {
+        return Ok((
+            StatusCode::TOO_MANY_REQUESTS,
+            Response::error("Too many failed login attempts"),
+        ));
+    }
+
+    if !unwrap_internal_error!(two_factor::verify_code(&mut transaction, user_id, &body.code).await)
+    {
+        // there are only a million codes, so guessing must not be an option
+        let result = sqlx::query("UPDATE two_factor_challenges SET attempts = attempts + 1 WHERE id = $1 RETURNING attempts")
+            .bind(challenge_id)
+            .fetch_one(&mut transaction)
+            .await;
+
+        if unwrap_internal_error!(result).get_unchecked::<i32, _>(0)
+            >= two_factor::CHALLENGE_MAX_ATTEMPTS
+        {
+            let result = sqlx::query("DELETE FROM two_factor_challenges WHERE id = $1")
+                .bind(challenge_id)
+                .execute(&mut transaction)
+                .await;
+
+            unwrap_internal_error!(result);
+        }
+
+        let event = audit::Event {
+            action: "two_factor_failed",
+            actor_id: None,
+            target_user_id: Some(user_id),
+            target_tweet_id: None,
+            details: None,
+        };
+
+        unwrap_internal_error!(audit::record(&mut transaction, event, request).await);
+        unwrap_internal_error!(transaction.commit().await);
+
+        let result = throttle::record_failure(&database, &username, client.address).await;
+
+        if unwrap_internal_error!(result) {
+            let event = audit::Event {
+                action: "account_locked",
+                actor_id: None,
+                target_user_id: Some(user_id),
+                target_tweet_id: None,
+                details: None,
+            };
+
+            unwrap_internal_error!(audit::record(&database, event, request).await);
+        }
+
+        return Ok(Response::unauthorized());
+    }
+
+    // every challenge can only be exchanged once
+    let result = sqlx::query("DELETE FROM two_factor_challenges WHERE id = $1")
+        .bind(challenge_id)
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+    unwrap_internal_error!(transaction.commit().await);
+
+    if unwrap_internal_error!(auth::is_suspended(&database, user_id).await) {
+        return Ok((
+            StatusCode::FORBIDDEN,
+            Response::error("Account is suspended"),
+        ));
+    }
+
+    unwrap_internal_error!(throttle::record_success(&database, &username).await);
+
+    let token = unwrap_internal_error!(auth::create_session(&database, user_id, request).await);
+
+    Ok((StatusCode::CREATED, Response::success(token)))
+}
@@ -0,0 +1,39 @@
+use crate::common::*;
+
+pub async fn get(request: &mut Request, database: Database) -> Result {
+    let session_id = check_auth_token!(request, database);
+
+    let (limit, cursor) = cursor_pagination!(request);
+
+    let result = sqlx::query("SELECT user_id FROM sessions WHERE id = $1")
+        .bind(session_id)
+        .fetch_optional(&database)
+        .await;
+
+    let user_id = match unwrap_internal_error!(result) {
+        Some(row) => row.get_unchecked::<i64, _>(0),
+        None => {
+            return Ok(Response::unauthorized());
+        }
+    };
+
+    let result = timeline::STORE
+        .page(&database, user_id, cursor, limit + 1)
+        .await;
+
+    let mut positions = unwrap_internal_error!(result);
+
+    let next_cursor = cursor::next(&mut positions, limit, |position| *position);
+
+    let ids = positions
+        .iter()
+        .map(|position| position.id)
+        .collect::<Vec<i64>>();
+
+    let tweets =
+        unwrap_internal_error!(tweet::fetch_ordered(&database, &ids, Some(session_id)).await);
+
+    let response = json!({ "tweets": tweets, "next_cursor": next_cursor });
+
+    Ok((StatusCode::OK, Response::success(response)))
+}
@@ -1,54 +1,38 @@
 use crate::common::*;
 
 pub async fn get(request: &mut Request, database: Database) -> Result {
-    let session_id = check_auth_token!(request);
+    // tweets of other users can be read without an account
+    let result = if request.uri().path() == "/users/@me/tweets" {
+        let session_id = check_auth_token!(request, database);
 
-    let mut limit = 50;
-    let mut offset = 0;
+        sqlx::query(&format!(
+            "SELECT {} FROM users WHERE id = (SELECT user_id FROM sessions WHERE id = $1)",
+            User::COLUMNS
+        ))
+        .bind(session_id)
+        .fetch_optional(&database)
+        .await
+    } else {
+        let user_id = parse_path_var!(request, i64, 1);
 
-    // why we still here?
-    if let Some(query) = request.uri().query() {
-        for pair in query.split("&").take(2) {
-            if let Some((key, value)) = pair.split_once("=") {
-                match key {
-                    "limit" => {
-                        if let Ok(value) = value.parse::<i32>() {
-                            if value < 0 || value > 50 {
-                                return Ok(Response::bad_request());
-                            } else {
-                                limit = value;
-                            }
-                        } else {
-                            return Ok(Response::bad_request());
-                        }
-                    }
-                    "offset" => {
-                        if let Ok(value) = value.parse::<i32>() {
-                            if value < 0 {
-                                return Ok(Response::bad_request());
-                            } else {
-                                offset = value;
-                            }
-                        } else {
-                            return Ok(Response::bad_request());
-                        }
-                    }
-                    _ => {
-                        return Ok(Response::bad_request());
-                    }
-                }
-            } else {
-                return Ok(Response::bad_request());
-            }
+        sqlx::query(&format!("SELECT {} FROM users WHERE id = $1 AND time_deactivated IS NULL AND time_suspended IS NULL", User::COLUMNS))
+            .bind(user_id)
+            .fetch_optional(&database)
+            .await
+    };
+
+    let (user_id, author) = match unwrap_internal_error!(result) {
+        Some(row) => (row.get_unchecked::<i64, _>(0), User::from_row(&row, 0)),
+        None => {
+            return Ok(Response::not_found());
         }
-    }
+    };
 
-    if limit < 0 || limit > 50 || offset < 0 {
-        return Ok(Response::bad_request());
-    }
+    let (limit, offset) = pagination!(request);
 
-    let result = sqlx::query("SELECT id, text, like_count, time_created FROM tweets WHERE user_id = (SELECT user_id FROM sessions WHERE id = $1) ORDER BY time_created DESC LIMIT $2 OFFSET $3")
-        .bind(session_id)
+    // retweets are listed along with the tweets, unless the author of the tweet can't be seen
+    let result = sqlx::query(&format!("SELECT posts.retweeted, posts.time_created, {}, {} FROM (SELECT id, time_created, false AS retweeted FROM tweets WHERE user_id = $1 UNION ALL SELECT tweet_id, time_created, true FROM retweets WHERE user_id = $1) AS posts JOIN tweets ON tweets.id = posts.id JOIN users ON users.id = tweets.user_id WHERE NOT posts.retweeted OR (users.time_deactivated IS NULL AND users.time_suspended IS NULL) ORDER BY posts.time_created DESC, posts.retweeted, posts.id DESC LIMIT $2 OFFSET $3", Tweet::COLUMNS, User::COLUMNS))
+        .bind(user_id)
         .bind(limit)
         .bind(offset)
         .fetch_all(&database)
@@ -57,12 +41,13 @@ pub async fn get(request: &mut Request, database: Database) -> Result {
     let response = unwrap_internal_error!(result)
         .into_iter()
         .map(|row| {
-            Tweet::new(
-                row.get_unchecked(0),
-                row.get_unchecked(1),
-                row.get_unchecked(2),
-                row.get_unchecked(3),
-            )
+            let tweet = Tweet::from_row(&row, 2).with_author(User::from_row(&row, 13));
+
+            if row.get_unchecked(0) {
+                tweet.with_retweeted_by(author.clone(), row.get_unchecked(1))
+            } else {
+                tweet
+            }
         })
         .collect::<Vec<Tweet>>();
 
@@ -4,7 +4,7 @@ pub async fn patch(request: &mut Request, database: Database) -> Result {
     check_content_type!(request);
 
     let id = parse_path_var!(request, i64);
-    let session_id = check_auth_token!(request);
+    let session_id = check_auth_token!(request, database);
 
     #[derive(serde::Deserialize)]
     pub struct Body {
@@ -17,22 +17,34 @@ pub async fn patch(request: &mut Request, database: Database) -> Result {
         return Ok(Response::bad_request());
     }
 
-    let result = sqlx::query("UPDATE tweets SET text = $1 WHERE id = $2 AND user_id = (SELECT user_id FROM sessions WHERE id = $3) RETURNING like_count, time_created")
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    let result = sqlx::query("UPDATE tweets SET text = $1 WHERE id = $2 AND user_id = (SELECT user_id FROM sessions WHERE id = $3)")
         .bind(&body.text)
         .bind(id)
         .bind(session_id)
-        .fetch_optional(&database)
+        .execute(&mut transaction)
         .await;
 
-    match unwrap_internal_error!(result) {
-        Some(row) => {
-            let like_count = row.get_unchecked(0);
-            let time_created = row.get_unchecked(1);
-            Ok((
-                StatusCode::OK,
-                Response::success(Tweet::new(id, body.text, like_count, time_created)),
-            ))
-        }
-        None => Ok(Response::not_found()),
+    if unwrap_internal_error!(result).rows_affected() == 0 {
+        return Ok(Response::not_found());
     }
+
+    // the entities are replaced along with the text, so their offsets always fit
+    let result = entities::store(&mut transaction, id, &body.text).await;
+    unwrap_internal_error!(result);
+
+    let result = sqlx::query(&format!(
+        "SELECT {} FROM tweets WHERE id = $1",
+        Tweet::COLUMNS
+    ))
+    .bind(id)
+    .fetch_one(&mut transaction)
+    .await;
+
+    let tweet = Tweet::from_row(&unwrap_internal_error!(result), 0);
+
+    unwrap_internal_error!(transaction.commit().await);
+
+    Ok((StatusCode::OK, Response::success(tweet)))
 }
@@ -3,11 +3,13 @@ use crate::common::*;
 pub async fn post(request: &mut Request, database: Database) -> Result {
     check_content_type!(request);
 
-    let session_id = check_auth_token!(request);
+    let session_id = check_auth_token!(request, database);
 
     #[derive(serde::Deserialize)]
     pub struct Body {
         text: String,
+        in_reply_to: Option<i64>,
+        quote: Option<i64>,
     }
 
     let body = body!(request, Body);
@@ -16,19 +18,88 @@ pub async fn post(request: &mut Request, database: Database) -> Result {
         return Ok(Response::bad_request());
     }
 
+    // nobody can reply to or quote tweets that can't be seen
+    for id in [body.in_reply_to, body.quote].into_iter().flatten() {
+        let result = sqlx::query("SELECT EXISTS (SELECT FROM tweets JOIN users ON users.id = tweets.user_id WHERE tweets.id = $1 AND users.time_deactivated IS NULL AND users.time_suspended IS NULL)")
+            .bind(id)
+            .fetch_one(&database)
+            .await;
+
+        if !unwrap_internal_error!(result).get_unchecked::<bool, _>(0) {
+            return Ok(Response::not_found());
+        }
+    }
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
     // this is still relatively efficient (in case you are wondering)
-    let result = sqlx::query("INSERT INTO tweets (user_id, text) VALUES ((SELECT user_id FROM sessions WHERE id = $1), $2) RETURNING id, time_created")
+    let result = sqlx::query("INSERT INTO tweets (user_id, text, in_reply_to_tweet_id, quoted_tweet_id) SELECT id, $2, $4, $5 FROM users WHERE id = (SELECT user_id FROM sessions WHERE id = $1) AND (email_verified OR NOT $3) RETURNING user_id, time_created, id")
         .bind(session_id)
         .bind(&body.text)
-        .fetch_one(&database)
+        .bind(*email::REQUIRE_VERIFIED_EMAIL)
+        .bind(body.in_reply_to)
+        .bind(body.quote)
+        .fetch_optional(&mut transaction)
         .await;
 
-    let row = unwrap_internal_error!(result);
-    let id = row.get_unchecked::<i64, _>(0);
+    let row = match result {
+        Ok(Some(row)) => row,
+        Ok(None) => {
+            return Ok((
+                StatusCode::FORBIDDEN,
+                Response::error("Email address is not verified"),
+            ));
+        }
+        Err(e) => {
+            if let Some(e) = e.as_database_error() {
+                if let Some(code) = e.code() {
+                    // FOREIGN KEY VIOLATION, the tweet was deleted in the meantime
+                    if code == "23503" {
+                        return Ok(Response::not_found());
+                    }
+                }
+            }
+
+            log::error!("{:?}", e);
+
+            return Ok(Response::internal_server_error());
+        }
+    };
+    let user_id = row.get_unchecked::<i64, _>(0);
     let time_created = row.get_unchecked::<i64, _>(1);
+    let id = row.get_unchecked::<i64, _>(2);
 
-    Ok((
-        StatusCode::CREATED,
-        Response::success(Tweet::new(id, body.text, 0, time_created)),
+    let result = entities::store(&mut transaction, id, &body.text).await;
+    unwrap_internal_error!(result);
+
+    let result = sqlx::query(&format!(
+        "SELECT {} FROM tweets WHERE id = $1",
+        Tweet::COLUMNS
     ))
+    .bind(id)
+    .fetch_one(&mut transaction)
+    .await;
+
+    let tweet = Tweet::from_row(&unwrap_internal_error!(result), 0);
+
+    unwrap_internal_error!(transaction.commit().await);
+
+    // a large account can have thousands of followers, so the tweet doesn't wait for the fan-out,
+    // and it's merged into the timelines when reading until the fan-out is done or if it fails
+    tokio::spawn(async move {
+        let result = match database.acquire().await {
+            Ok(mut connection) => {
+                timeline::STORE
+                    .insert(&mut connection, user_id, id, time_created)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = result {
+            log::warn!("Failed to fan out tweet {}: {}", id, e);
+        }
+    });
+
+    Ok((StatusCode::CREATED, Response::success(tweet)))
 }
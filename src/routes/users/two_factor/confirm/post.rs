@@ -0,0 +1,112 @@
+use crate::common::*;
+
+pub async fn post(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    let session_id = check_auth_token!(request, database);
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        code: String,
+    }
+
+    let body = body!(request, Body);
+
+    let result = sqlx::query("SELECT id, username, totp_enabled, totp_secret IS NOT NULL FROM users WHERE id = (SELECT user_id FROM sessions WHERE id = $1)")
+        .bind(session_id)
+        .fetch_one(&database)
+        .await;
+
+    let row = unwrap_internal_error!(result);
+    let user_id = row.get_unchecked::<i64, _>(0);
+    let username = row.get_unchecked::<String, _>(1);
+
+    if row.get_unchecked::<bool, _>(2) {
+        return Ok((
+            StatusCode::CONFLICT,
+            Response::error("Two-factor authentication is already enabled"),
+        ));
+    }
+
+    if !row.get_unchecked::<bool, _>(3) {
+        return Ok(Response::not_found());
+    }
+
+    let client = client!(request);
+
+    // there are only a million codes, so a stolen session must not be able to guess its way
+    // into replacing the recovery codes
+    if unwrap_internal_error!(throttle::is_locked(&database, &username, client.address).await) {
+        return Ok((
+            StatusCode::TOO_MANY_REQUESTS,
+            Response::error("Too many failed login attempts"),
+        ));
+    }
+
+    let mut connection = unwrap_internal_error!(database.acquire().await);
+
+    if !unwrap_internal_error!(two_factor::verify_code(&mut connection, user_id, &body.code).await)
+    {
+        let result = throttle::record_failure(&database, &username, client.address).await;
+
+        if unwrap_internal_error!(result) {
+            let event = audit::Event {
+                action: "account_locked",
+                actor_id: None,
+                target_user_id: Some(user_id),
+                target_tweet_id: None,
+                details: None,
+            };
+
+            unwrap_internal_error!(audit::record(&database, event, request).await);
+        }
+
+        return Ok((StatusCode::FORBIDDEN, Response::error("Invalid code")));
+    }
+
+    unwrap_internal_error!(throttle::record_success(&database, &username).await);
+
+    let recovery_codes = (0..two_factor::RECOVERY_CODE_COUNT)
+        .map(|_| two_factor::generate_recovery_code())
+        .collect::<Vec<String>>();
+
+    let hashes = recovery_codes
+        .iter()
+        .map(|code| two_factor::hash_recovery_code(code))
+        .collect::<Vec<String>>();
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    let result = sqlx::query("UPDATE users SET totp_enabled = true WHERE id = $1")
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+
+    let result = sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+
+    let result = sqlx::query(
+        "INSERT INTO recovery_codes (user_id, code_hash) SELECT $1, unnest($2::text[])",
+    )
+    .bind(user_id)
+    .bind(hashes)
+    .execute(&mut transaction)
+    .await;
+
+    unwrap_internal_error!(result);
+
+    let result =
+        audit::record_event(&mut transaction, user_id, "two_factor_enabled", request).await;
+
+    unwrap_internal_error!(result);
+    unwrap_internal_error!(transaction.commit().await);
+
+    // only the hashes are stored, so this is the only time the user gets to see the codes
+    Ok((StatusCode::OK, Response::success(recovery_codes)))
+}
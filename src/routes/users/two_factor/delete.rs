@@ -0,0 +1,98 @@
+use crate::common::*;
+
+pub async fn delete(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    let session_id = check_auth_token!(request, database);
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        code: String,
+        password: String,
+    }
+
+    let body = body!(request, Body);
+
+    let result = sqlx::query("SELECT id, username, password_hash, totp_enabled FROM users WHERE id = (SELECT user_id FROM sessions WHERE id = $1)")
+        .bind(session_id)
+        .fetch_one(&database)
+        .await;
+
+    let row = unwrap_internal_error!(result);
+    let user_id = row.get_unchecked::<i64, _>(0);
+    let username = row.get_unchecked::<String, _>(1);
+    let hash = row.get_unchecked::<Option<String>, _>(2);
+
+    if !row.get_unchecked::<bool, _>(3) {
+        return Ok(Response::not_found());
+    }
+
+    let client = client!(request);
+
+    if unwrap_internal_error!(throttle::is_locked(&database, &username, client.address).await) {
+        return Ok((
+            StatusCode::TOO_MANY_REQUESTS,
+            Response::error("Too many failed login attempts"),
+        ));
+    }
+
+    // a stolen session alone must not be enough to turn it off, and neither must guessing
+    // the code with it, so failures lock out the username just like failed logins
+    let message = if !unwrap_internal_error!(password::verify_optional(body.password, hash).await) {
+        Some("Wrong password")
+    } else {
+        let mut connection = unwrap_internal_error!(database.acquire().await);
+
+        if !unwrap_internal_error!(
+            two_factor::verify_code(&mut connection, user_id, &body.code).await
+        ) {
+            Some("Invalid code")
+        } else {
+            None
+        }
+    };
+
+    if let Some(message) = message {
+        let result = throttle::record_failure(&database, &username, client.address).await;
+
+        if unwrap_internal_error!(result) {
+            let event = audit::Event {
+                action: "account_locked",
+                actor_id: None,
+                target_user_id: Some(user_id),
+                target_tweet_id: None,
+                details: None,
+            };
+
+            unwrap_internal_error!(audit::record(&database, event, request).await);
+        }
+
+        return Ok((StatusCode::FORBIDDEN, Response::error(message)));
+    }
+
+    unwrap_internal_error!(throttle::record_success(&database, &username).await);
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    let result = sqlx::query("UPDATE users SET totp_enabled = false, totp_secret = NULL, totp_last_step = NULL WHERE id = $1")
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+
+    let result = sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+
+    let result =
+        audit::record_event(&mut transaction, user_id, "two_factor_disabled", request).await;
+
+    unwrap_internal_error!(result);
+    unwrap_internal_error!(transaction.commit().await);
+
+    Ok((StatusCode::OK, Response::empty()))
+}
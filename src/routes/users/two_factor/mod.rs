@@ -0,0 +1,7 @@
+pub mod confirm;
+
+mod delete;
+mod post;
+
+pub use delete::delete;
+pub use post::post;
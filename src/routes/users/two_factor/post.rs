@@ -0,0 +1,82 @@
+use crate::common::*;
+
+pub async fn post(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    let session_id = check_auth_token!(request, database);
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        password: String,
+    }
+
+    let body = body!(request, Body);
+
+    let result = sqlx::query(
+        "SELECT id, username, password_hash, totp_enabled FROM users WHERE id = (SELECT user_id FROM sessions WHERE id = $1)",
+    )
+    .bind(session_id)
+    .fetch_one(&database)
+    .await;
+
+    let row = unwrap_internal_error!(result);
+    let user_id = row.get_unchecked::<i64, _>(0);
+    let username = row.get_unchecked::<String, _>(1);
+    let hash = row.get_unchecked::<Option<String>, _>(2);
+
+    if row.get_unchecked::<bool, _>(3) {
+        return Ok((
+            StatusCode::CONFLICT,
+            Response::error("Two-factor authentication is already enabled"),
+        ));
+    }
+
+    let client = client!(request);
+
+    if unwrap_internal_error!(throttle::is_locked(&database, &username, client.address).await) {
+        return Ok((
+            StatusCode::TOO_MANY_REQUESTS,
+            Response::error("Too many failed login attempts"),
+        ));
+    }
+
+    // a stolen session alone must not be enough to enroll a secret of the attacker,
+    // that would lock the owner out of their own account
+    if !unwrap_internal_error!(password::verify_optional(body.password, hash).await) {
+        let result = throttle::record_failure(&database, &username, client.address).await;
+
+        if unwrap_internal_error!(result) {
+            let event = audit::Event {
+                action: "account_locked",
+                actor_id: None,
+                target_user_id: Some(user_id),
+                target_tweet_id: None,
+                details: None,
+            };
+
+            unwrap_internal_error!(audit::record(&database, event, request).await);
+        }
+
+        return Ok((StatusCode::FORBIDDEN, Response::error("Wrong password")));
+    }
+
+    unwrap_internal_error!(throttle::record_success(&database, &username).await);
+
+    // the secret stays pending until a code generated from it is confirmed
+    let secret = two_factor::generate_secret();
+
+    let result =
+        sqlx::query("UPDATE users SET totp_secret = $1, totp_last_step = NULL WHERE id = $2")
+            .bind(&secret)
+            .bind(user_id)
+            .execute(&database)
+            .await;
+
+    unwrap_internal_error!(result);
+
+    let provisioning_uri = two_factor::provisioning_uri(&username, &secret);
+
+    let response = json!({ "secret": secret, "provisioning_uri": provisioning_uri });
+
+    Ok((StatusCode::CREATED, Response::success(response)))
+}
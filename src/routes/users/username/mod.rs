@@ -0,0 +1,3 @@
+mod patch;
+
+pub use patch::patch;
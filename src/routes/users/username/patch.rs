@@ -0,0 +1,109 @@
+use crate::common::*;
+
+pub async fn patch(request: &mut Request, database: Database) -> Result {
+    check_content_type!(request);
+
+    let session_id = check_auth_token!(request, database);
+
+    #[derive(serde::Deserialize)]
+    struct Body {
+        username: String,
+    }
+
+    let body = body!(request, Body);
+
+    let new_username = username::normalize(&body.username);
+
+    if username::is_invalid(&new_username) {
+        return Ok(Response::bad_request());
+    }
+
+    if username::is_reserved(&new_username) {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Response::error("Username is reserved"),
+        ));
+    }
+
+    let mut transaction = unwrap_internal_error!(database.begin().await);
+
+    // lock the row, so that concurrent changes can't both pass the cooldown check
+    let result = sqlx::query(&format!(
+        "SELECT {} FROM users WHERE id = (SELECT user_id FROM sessions WHERE id = $1) FOR UPDATE",
+        User::COLUMNS
+    ))
+    .bind(session_id)
+    .fetch_optional(&mut transaction)
+    .await;
+
+    let row = match unwrap_internal_error!(result) {
+        Some(row) => row,
+        None => {
+            return Ok(Response::unauthorized());
+        }
+    };
+
+    let user_id = row.get_unchecked::<i64, _>(0);
+    let old_username = row.get_unchecked::<String, _>(1);
+
+    if new_username == old_username {
+        return Ok((StatusCode::OK, Response::success(User::from_row(&row, 0))));
+    }
+
+    let result = sqlx::query("SELECT EXISTS (SELECT FROM username_history WHERE user_id = $1 AND time_changed > extract(EPOCH FROM now()) - $2)")
+        .bind(user_id)
+        .bind(*username::CHANGE_COOLDOWN)
+        .fetch_one(&mut transaction)
+        .await;
+
+    if unwrap_internal_error!(result).get_unchecked::<bool, _>(0) {
+        return Ok((
+            StatusCode::TOO_MANY_REQUESTS,
+            Response::error("Username was changed recently"),
+        ));
+    }
+
+    let result = username::is_held(&mut transaction, &new_username, Some(user_id)).await;
+
+    if unwrap_internal_error!(result) {
+        return Ok((
+            StatusCode::CONFLICT,
+            Response::error("Username already exists"),
+        ));
+    }
+
+    let result = sqlx::query(&format!(
+        "UPDATE users SET username = $1 WHERE id = $2 RETURNING {}",
+        User::COLUMNS
+    ))
+    .bind(&new_username)
+    .bind(user_id)
+    .fetch_one(&mut transaction)
+    .await;
+
+    if let Err(sqlx::Error::Database(e)) = &result {
+        if e.code().as_deref() == Some("23505") {
+            return Ok((
+                StatusCode::CONFLICT,
+                Response::error("Username already exists"),
+            ));
+        }
+    }
+
+    let user = User::from_row(&unwrap_internal_error!(result), 0);
+
+    let result = sqlx::query("INSERT INTO username_history (user_id, username) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind(&old_username)
+        .execute(&mut transaction)
+        .await;
+
+    unwrap_internal_error!(result);
+
+    let result = audit::record_event(&mut transaction, user_id, "username_changed", request).await;
+
+    unwrap_internal_error!(result);
+    unwrap_internal_error!(transaction.commit().await);
+
+    Ok((StatusCode::OK, Response::success(user)))
+}
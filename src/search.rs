@@ -0,0 +1,238 @@
+/// The longest search query, before decoding.
+pub const MAX_QUERY_LENGTH: usize = 512;
+
+/// A search query, split into the text to search for and the operators that filter the results.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Query {
+    /// Handed to `websearch_to_tsquery`, which understands quoted phrases, `OR` and `-` for exclusion.
+    pub text: String,
+    pub from: Option<String>,
+    pub hashtags: Vec<String>,
+    /// Inclusive, in UNIX time.
+    pub since: Option<i64>,
+    /// Exclusive, in UNIX time.
+    pub until: Option<i64>,
+}
+
+/// Also the tag of the cursors, so that a cursor of one order can't be used with the other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sort {
+    Relevance = 0,
+    Recency = 1,
+}
+
+impl std::str::FromStr for Sort {
+    type Err = ();
+
+    fn from_str(val: &str) -> Result<Self, Self::Err> {
+        match val {
+            "relevance" => Ok(Self::Relevance),
+            "recency" => Ok(Self::Recency),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Decodes a value of a query string, where spaces may come as `+`.
+pub fn decode_query_value(val: &str) -> Option<String> {
+    let bytes = val.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 2;
+            }
+            byte => decoded.push(byte),
+        }
+
+        i += 1;
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+/// The UNIX time of the start of a day given as `YYYY-MM-DD`, in UTC.
+pub fn parse_date(date: &str) -> Option<i64> {
+    let mut parts = date.splitn(3, '-');
+
+    let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return None;
+    }
+
+    let year = year.parse::<i64>().ok()?;
+    let month = month.parse::<i64>().ok()?;
+    let day = day.parse::<i64>().ok()?;
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => {
+            return None;
+        }
+    };
+
+    if day < 1 || day > days_in_month {
+        return None;
+    }
+
+    // https://howardhinnant.github.io/date_algorithms.html#days_from_civil
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    Some((era * 146097 + day_of_era - 719468) * 24 * 60 * 60)
+}
+
+/// Splits off the operators: `from:username`, `#hashtag`, `since:YYYY-MM-DD` and `until:YYYY-MM-DD`.
+/// Nothing in quotes is an operator. Returns `None` if an operator is malformed or repeated, or if
+/// there is nothing to search for.
+pub fn parse(query: &str) -> Option<Query> {
+    let mut parsed = Query::default();
+    let mut text = Vec::new();
+    let mut in_quotes = false;
+    let mut token = String::new();
+
+    // a trailing space flushes the last token
+    for c in query.chars().chain(std::iter::once(' ')) {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        }
+
+        if !c.is_whitespace() || in_quotes {
+            token.push(c);
+            continue;
+        }
+
+        if token.is_empty() {
+            continue;
+        }
+
+        let (operator, value) = token.split_once(':').unwrap_or_default();
+
+        match operator.to_lowercase().as_str() {
+            _ if token.starts_with('"') => text.push(std::mem::take(&mut token)),
+            "from" if parsed.from.is_none() => {
+                if value.is_empty()
+                    || value.len() > crate::username::MAX_LENGTH
+                    || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                {
+                    return None;
+                }
+
+                parsed.from = Some(value.to_owned());
+            }
+            "since" if parsed.since.is_none() => parsed.since = Some(parse_date(value)?),
+            "until" if parsed.until.is_none() => parsed.until = Some(parse_date(value)?),
+            "from" | "since" | "until" => {
+                return None;
+            }
+            _ => {
+                let entities = crate::entities::extract(&token);
+
+                match entities.as_slice() {
+                    [entity]
+                        if entity.kind == crate::entities::Kind::Hashtag
+                            && entity.start == 0
+                            && entity.end == token.chars().count() =>
+                    {
+                        parsed.hashtags.push(entity.value.clone());
+                    }
+                    _ => text.push(token.clone()),
+                }
+            }
+        }
+
+        token.clear();
+    }
+
+    parsed.text = text.join(" ");
+
+    if parsed.text.is_empty()
+        && parsed.from.is_none()
+        && parsed.hashtags.is_empty()
+        && parsed.since.is_none()
+        && parsed.until.is_none()
+    {
+        return None;
+    }
+
+    Some(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search::*;
+
+    #[test]
+    fn test_decode_query_value() {
+        assert_eq!(decode_query_value("a+b%20c"), Some("a b c".to_owned()));
+        assert_eq!(
+            decode_query_value("%23rust%3A%C3%A4"),
+            Some("#rust:ä".to_owned())
+        );
+        assert_eq!(decode_query_value("%2"), None);
+        assert_eq!(decode_query_value("%zz"), None);
+        assert_eq!(decode_query_value("%ff"), None);
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2022-11-23"), Some(1669161600));
+        assert_eq!(parse_date("2024-02-29"), Some(1709164800));
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2023-13-01"), None);
+        assert_eq!(parse_date("2023-1-01"), None);
+        assert_eq!(parse_date("yesterday"), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("hello \"big world\" from:Hello #Rust since:2022-11-23 until:2022-11-24 -bye"),
+            Some(Query {
+                text: "hello \"big world\" -bye".to_owned(),
+                from: Some("Hello".to_owned()),
+                hashtags: vec!["Rust".to_owned()],
+                since: Some(1669161600),
+                until: Some(1669248000),
+            })
+        );
+
+        // operators in quotes are searched for, and so are tokens that only contain hashtags
+        assert_eq!(
+            parse("\"from:hello #rust\" #rust!"),
+            Some(Query {
+                text: "\"from:hello #rust\" #rust!".to_owned(),
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(
+            parse("from:hello"),
+            Some(Query {
+                from: Some("hello".to_owned()),
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("   "), None);
+        assert_eq!(parse("from:"), None);
+        assert_eq!(parse("from:a from:b"), None);
+        assert_eq!(parse("from:héllo"), None);
+        assert_eq!(parse("since:tomorrow"), None);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::common::*;
+
+/// Deletes the rows matched by `sql` once an hour, `bind` being its only parameter.
+async fn purge(database: Database, name: &str, sql: &str, bind: i64) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+
+    loop {
+        interval.tick().await;
+
+        match sqlx::query(sql).bind(bind).execute(&database).await {
+            Ok(result) => {
+                if result.rows_affected() != 0 {
+                    log::info!("Purged {} {}", result.rows_affected(), name);
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed to purge {}: {}", name, e);
+            }
+        }
+    }
+}
+
+/// Hard-deletes deactivated accounts once their grace period is over.
+pub async fn purge_deactivated_users(database: Database) {
+    // likes of the purged users are removed by ON DELETE CASCADE,
+    // the update_like_count trigger keeps like counts of the remaining tweets in sync
+    purge(
+        database,
+        "deactivated users",
+        "DELETE FROM users WHERE time_deactivated <= extract(EPOCH FROM now()) - $1",
+        *ACCOUNT_GRACE_PERIOD,
+    )
+    .await
+}
+
+/// Forgets solved challenges once they have expired and can't be replayed anyway.
+pub async fn purge_spent_challenges(database: Database) {
+    purge(
+        database,
+        "spent challenges",
+        "DELETE FROM spent_challenges WHERE time_expires <= extract(EPOCH FROM now()) - $1",
+        0,
+    )
+    .await
+}
+
+/// Removes failed login records that no longer affect anything.
+pub async fn purge_login_throttles(database: Database) {
+    purge(
+        database,
+        "login throttles",
+        "DELETE FROM login_throttles WHERE time_last_failure <= extract(EPOCH FROM now()) - $1 AND time_locked_until <= extract(EPOCH FROM now())",
+        throttle::FAILURE_WINDOW,
+    )
+    .await
+}
+
+/// Removes two-factor login challenges that have expired.
+pub async fn purge_two_factor_challenges(database: Database) {
+    purge(
+        database,
+        "two-factor challenges",
+        "DELETE FROM two_factor_challenges WHERE time_created <= extract(EPOCH FROM now()) - $1",
+        two_factor::CHALLENGE_LIFETIME,
+    )
+    .await
+}
+
+/// Removes password reset tokens that can't be used anymore.
+pub async fn purge_password_resets(database: Database) {
+    purge(
+        database,
+        "password resets",
+        "DELETE FROM password_resets WHERE time_created <= extract(EPOCH FROM now()) - $1",
+        password_reset::TOKEN_LIFETIME,
+    )
+    .await
+}
+
+/// Removes email verifications that can't be used anymore, along with the addresses they carry.
+pub async fn purge_email_verifications(database: Database) {
+    purge(
+        database,
+        "email verifications",
+        "DELETE FROM email_verifications WHERE time_created <= extract(EPOCH FROM now()) - $1",
+        email::VERIFICATION_LIFETIME,
+    )
+    .await
+}
+
+/// Removes OpenID Connect logins that were never completed.
+pub async fn purge_oidc_logins(database: Database) {
+    purge(
+        database,
+        "OpenID Connect logins",
+        "DELETE FROM oidc_logins WHERE time_created <= extract(EPOCH FROM now()) - $1",
+        oidc::LOGIN_LIFETIME,
+    )
+    .await
+}
+
+/// Scrubs the client details of audit events once the retention period is over, so that they
+/// don't outlive the accounts they belong to. The trigger on audit_events allows nothing else.
+pub async fn scrub_audit_events(database: Database) {
+    purge(
+        database,
+        "client details from audit events",
+        "UPDATE audit_events SET ip_address = NULL, user_agent = NULL WHERE time_created <= extract(EPOCH FROM now()) - $1 AND (ip_address IS NOT NULL OR user_agent IS NOT NULL)",
+        *audit::RETENTION,
+    )
+    .await
+}
+
+/// Extracts the entities of the tweet, unless that has happened since it was picked.
+async fn extract_tweet_entities(
+    database: &Database,
+    id: i64,
+) -> std::result::Result<(), sqlx::Error> {
+    let mut transaction = database.begin().await?;
+
+    // the tweet might have been edited or deleted in the meantime, so the text is read again
+    // and kept from changing until the entities are stored
+    let row =
+        sqlx::query("SELECT text FROM tweets WHERE id = $1 AND NOT entities_extracted FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut transaction)
+            .await?;
+
+    let text = match row {
+        Some(row) => row
+            .get_unchecked::<Option<String>, _>(0)
+            .unwrap_or_default(),
+        None => {
+            return Ok(());
+        }
+    };
+
+    entities::store(&mut transaction, id, &text).await?;
+
+    transaction.commit().await
+}
+
+/// Extracts the entities of the tweets that were created before there were any, a batch at a time.
+pub async fn extract_entities(database: Database) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+
+    loop {
+        interval.tick().await;
+
+        let result = sqlx::query(
+            "SELECT id FROM tweets WHERE NOT entities_extracted ORDER BY id LIMIT 1000",
+        )
+        .fetch_all(&database)
+        .await;
+
+        let rows = match result {
+            Ok(rows) => rows,
+            Err(e) => {
+                log::warn!("Failed to find tweets without entities: {}", e);
+                continue;
+            }
+        };
+
+        for row in rows {
+            let id = row.get_unchecked::<i64, _>(0);
+
+            if let Err(e) = extract_tweet_entities(&database, id).await {
+                log::warn!("Failed to extract the entities of tweet {}: {}", id, e);
+                break;
+            }
+        }
+    }
+}
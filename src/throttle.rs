@@ -0,0 +1,143 @@
+use crate::common::Database;
+use crate::env_param;
+use once_cell::sync::Lazy;
+use sqlx::Row;
+
+static MAX_FAILURES_PER_USERNAME: Lazy<i32> = Lazy::new(|| env_param!("LOGIN_MAX_FAILURES", 5));
+
+// a lot of users can share the same address (think of an office behind a NAT)
+static MAX_FAILURES_PER_IP: Lazy<i32> = Lazy::new(|| env_param!("LOGIN_MAX_FAILURES_PER_IP", 20));
+
+static LOCKOUT: Lazy<i64> = Lazy::new(|| env_param!("LOGIN_LOCKOUT_SECONDS", 30));
+
+static MAX_LOCKOUT: Lazy<i64> = Lazy::new(|| env_param!("LOGIN_MAX_LOCKOUT_SECONDS", 60 * 60));
+
+/// Failures older than that are forgotten.
+pub const FAILURE_WINDOW: i64 = 24 * 60 * 60;
+
+#[inline(always)]
+fn username_subject(username: &str) -> String {
+    // usernames are case-insensitive, so are their failures
+    format!("username:{}", username.to_lowercase())
+}
+
+#[inline(always)]
+fn ip_subject(address: std::net::IpAddr) -> String {
+    format!("ip:{}", address)
+}
+
+/// The lockout duration after the given number of consecutive failures.
+#[inline(always)]
+pub fn lockout_duration(
+    failure_count: i32,
+    max_failures: i32,
+    lockout: i64,
+    max_lockout: i64,
+) -> i64 {
+    if failure_count < max_failures {
+        return 0;
+    }
+
+    // every failure past the limit doubles the lockout
+    let exponent = (failure_count - max_failures).min(32) as u32;
+
+    lockout.saturating_mul(1 << exponent).min(max_lockout)
+}
+
+/// Returns `true` if either the username or the address is locked out.
+pub async fn is_locked(
+    database: &Database,
+    username: &str,
+    address: std::net::IpAddr,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query("SELECT EXISTS (SELECT FROM login_throttles WHERE subject IN ($1, $2) AND time_locked_until > extract(EPOCH FROM now()))")
+        .bind(username_subject(username))
+        .bind(ip_subject(address))
+        .fetch_one(database)
+        .await?;
+
+    Ok(row.get_unchecked::<bool, _>(0))
+}
+
+async fn record(
+    database: &Database,
+    subject: String,
+    max_failures: i32,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query("INSERT INTO login_throttles (subject) VALUES ($1) ON CONFLICT (subject) DO UPDATE SET failure_count = CASE WHEN login_throttles.time_last_failure > extract(EPOCH FROM now()) - $2 THEN login_throttles.failure_count + 1 ELSE 1 END, time_last_failure = extract(EPOCH FROM now()) RETURNING failure_count")
+        .bind(&subject)
+        .bind(FAILURE_WINDOW)
+        .fetch_one(database)
+        .await?;
+
+    let duration = lockout_duration(row.get_unchecked(0), max_failures, *LOCKOUT, *MAX_LOCKOUT);
+
+    if duration == 0 {
+        return Ok(false);
+    }
+
+    sqlx::query("UPDATE login_throttles SET time_locked_until = extract(EPOCH FROM now()) + $1 WHERE subject = $2")
+        .bind(duration)
+        .bind(&subject)
+        .execute(database)
+        .await?;
+
+    log::warn!("Locked out {} for {} seconds", subject, duration);
+
+    Ok(true)
+}
+
+/// The number of recent failed logins from the address, whether it's locked out or not.
+pub async fn ip_failure_count(
+    database: &Database,
+    address: std::net::IpAddr,
+) -> Result<i32, sqlx::Error> {
+    let row = sqlx::query("SELECT failure_count FROM login_throttles WHERE subject = $1 AND time_last_failure > extract(EPOCH FROM now()) - $2")
+        .bind(ip_subject(address))
+        .bind(FAILURE_WINDOW)
+        .fetch_optional(database)
+        .await?;
+
+    Ok(row.map_or(0, |row| row.get_unchecked(0)))
+}
+
+/// Records a failed login. Returns `true` if the username got locked out.
+pub async fn record_failure(
+    database: &Database,
+    username: &str,
+    address: std::net::IpAddr,
+) -> Result<bool, sqlx::Error> {
+    record(database, ip_subject(address), *MAX_FAILURES_PER_IP).await?;
+    record(
+        database,
+        username_subject(username),
+        *MAX_FAILURES_PER_USERNAME,
+    )
+    .await
+}
+
+/// Forgets the failed logins of the username. Failures of the address are kept,
+/// otherwise a single valid account would be enough to keep guessing forever.
+pub async fn record_success(database: &Database, username: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM login_throttles WHERE subject = $1")
+        .bind(username_subject(username))
+        .execute(database)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_lockout_duration() {
+        use crate::throttle::lockout_duration;
+
+        assert_eq!(lockout_duration(1, 5, 30, 3600), 0);
+        assert_eq!(lockout_duration(4, 5, 30, 3600), 0);
+        assert_eq!(lockout_duration(5, 5, 30, 3600), 30);
+        assert_eq!(lockout_duration(6, 5, 30, 3600), 60);
+        assert_eq!(lockout_duration(8, 5, 30, 3600), 240);
+        assert_eq!(lockout_duration(100, 5, 30, 3600), 3600);
+    }
+}
@@ -0,0 +1,257 @@
+use crate::common::*;
+use crate::cursor::Cursor;
+use once_cell::sync::Lazy;
+
+type BoxFuture<'a, T> = std::pin::Pin<
+    Box<dyn std::future::Future<Output = std::result::Result<T, sqlx::Error>> + Send + 'a>,
+>;
+
+/// Keeps the home timelines of the users: their own tweets and the ones of the users they follow.
+/// Writes take a connection, so that follows and unfollows can be part of the transaction that caused
+/// them. Tweets are inserted after their transaction, in the background.
+pub trait TimelineStore: Send + Sync {
+    /// Called once a tweet was created.
+    fn insert<'a>(
+        &'a self,
+        connection: &'a mut sqlx::PgConnection,
+        author_id: i64,
+        tweet_id: i64,
+        time_created: i64,
+    ) -> BoxFuture<'a, ()>;
+
+    /// Called once the follow was created.
+    fn follow<'a>(
+        &'a self,
+        connection: &'a mut sqlx::PgConnection,
+        follower_id: i64,
+        followee_id: i64,
+    ) -> BoxFuture<'a, ()>;
+
+    /// Called once the follow was deleted.
+    fn unfollow<'a>(
+        &'a self,
+        connection: &'a mut sqlx::PgConnection,
+        follower_id: i64,
+        followee_id: i64,
+    ) -> BoxFuture<'a, ()>;
+
+    /// Returns the positions of up to `limit` tweets after the cursor, newest first.
+    /// Tweets of deactivated and suspended users are left out.
+    fn page<'a>(
+        &'a self,
+        database: &'a Database,
+        user_id: i64,
+        cursor: Cursor,
+        limit: i32,
+    ) -> BoxFuture<'a, Vec<Cursor>>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    Hybrid,
+    Read,
+}
+
+impl std::str::FromStr for Kind {
+    type Err = ();
+
+    fn from_str(val: &str) -> std::result::Result<Self, Self::Err> {
+        match val {
+            "hybrid" => Ok(Self::Hybrid),
+            "read" => Ok(Self::Read),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Accounts with more followers than that don't copy their tweets into the timelines of all of them,
+/// their tweets are merged in when reading instead.
+static FANOUT_LIMIT: Lazy<i32> = Lazy::new(|| env_param!("TIMELINE_FANOUT_LIMIT", 10_000));
+
+/// How many of the latest tweets of a user are copied into the timeline of a new follower.
+const MAX_BACKFILL: i64 = 1000;
+
+pub static STORE: Lazy<Box<dyn TimelineStore>> = Lazy::new(|| {
+    let kind = std::env::var("TIMELINE_STORE")
+        .ok()
+        .map(|kind| kind.parse().expect("Invalid TIMELINE_STORE"))
+        .unwrap_or(Kind::Hybrid);
+
+    match kind {
+        Kind::Hybrid => Box::new(HybridStore),
+        Kind::Read => Box::new(ReadStore),
+    }
+});
+
+/// Copies tweets into the timelines of the followers when they are created, unless the author has
+/// too many of them. Tweets that weren't copied for whatever reason are merged in when reading, so
+/// the timelines stay complete when accounts grow or shrink, or when the store is swapped.
+pub struct HybridStore;
+
+impl TimelineStore for HybridStore {
+    fn insert<'a>(
+        &'a self,
+        connection: &'a mut sqlx::PgConnection,
+        author_id: i64,
+        tweet_id: i64,
+        time_created: i64,
+    ) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            let mut transaction = sqlx::Connection::begin(connection).await?;
+
+            // a follow of the author waits for the fan-out or the other way around, so that the
+            // follower gets the tweet either from the fan-out or from the backfill
+            sqlx::query("SELECT FROM users WHERE id = $1 FOR SHARE")
+                .bind(author_id)
+                .execute(&mut transaction)
+                .await?;
+
+            let result = sqlx::query("INSERT INTO timelines (user_id, tweet_id, time_created) SELECT follower_id, $2, $3 FROM follows WHERE followee_id = $1 AND (SELECT follower_count FROM users WHERE id = $1) <= $4 UNION ALL SELECT $1, $2, $3 WHERE (SELECT follower_count FROM users WHERE id = $1) <= $4")
+                .bind(author_id)
+                .bind(tweet_id)
+                .bind(time_created)
+                .bind(*FANOUT_LIMIT)
+                .execute(&mut transaction)
+                .await?;
+
+            if result.rows_affected() > 0 {
+                sqlx::query("UPDATE tweets SET fanned_out = true WHERE id = $1")
+                    .bind(tweet_id)
+                    .execute(&mut transaction)
+                    .await?;
+            }
+
+            transaction.commit().await
+        })
+    }
+
+    fn follow<'a>(
+        &'a self,
+        connection: &'a mut sqlx::PgConnection,
+        follower_id: i64,
+        followee_id: i64,
+    ) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            // waits for fan-outs of the followee that are in progress, see insert
+            sqlx::query("SELECT FROM users WHERE id = $1 FOR UPDATE")
+                .bind(followee_id)
+                .execute(&mut *connection)
+                .await?;
+
+            sqlx::query("INSERT INTO timelines (user_id, tweet_id, time_created) SELECT $1, id, time_created FROM tweets WHERE user_id = $2 AND fanned_out ORDER BY time_created DESC LIMIT $3 ON CONFLICT DO NOTHING")
+                .bind(follower_id)
+                .bind(followee_id)
+                .bind(MAX_BACKFILL)
+                .execute(connection)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    fn unfollow<'a>(
+        &'a self,
+        connection: &'a mut sqlx::PgConnection,
+        follower_id: i64,
+        followee_id: i64,
+    ) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            sqlx::query("DELETE FROM timelines WHERE user_id = $1 AND tweet_id IN (SELECT id FROM tweets WHERE user_id = $2)")
+                .bind(follower_id)
+                .bind(followee_id)
+                .execute(connection)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    fn page<'a>(
+        &'a self,
+        database: &'a Database,
+        user_id: i64,
+        cursor: Cursor,
+        limit: i32,
+    ) -> BoxFuture<'a, Vec<Cursor>> {
+        Box::pin(async move {
+            // both halves are cut to the limit on their own, so that each can use its index
+            let rows = sqlx::query("SELECT id, time_created FROM ((SELECT timelines.tweet_id AS id, timelines.time_created FROM timelines JOIN tweets ON tweets.id = timelines.tweet_id JOIN users ON users.id = tweets.user_id WHERE timelines.user_id = $1 AND (timelines.time_created, timelines.tweet_id) < ($2, $3) AND users.time_deactivated IS NULL AND users.time_suspended IS NULL ORDER BY timelines.time_created DESC, timelines.tweet_id DESC LIMIT $4) UNION (SELECT tweets.id, tweets.time_created FROM tweets JOIN users ON users.id = tweets.user_id WHERE NOT tweets.fanned_out AND (tweets.user_id = $1 OR tweets.user_id IN (SELECT followee_id FROM follows WHERE follower_id = $1)) AND (tweets.time_created, tweets.id) < ($2, $3) AND users.time_deactivated IS NULL AND users.time_suspended IS NULL ORDER BY tweets.time_created DESC, tweets.id DESC LIMIT $4)) AS timeline ORDER BY time_created DESC, id DESC LIMIT $4")
+                .bind(user_id)
+                .bind(cursor.time)
+                .bind(cursor.id)
+                .bind(limit)
+                .fetch_all(database)
+                .await?;
+
+            Ok(rows
+                .into_iter()
+                .map(|row| Cursor {
+                    time: row.get_unchecked(1),
+                    id: row.get_unchecked(0),
+                })
+                .collect())
+        })
+    }
+}
+
+/// Merges the tweets of all followed users on every read. Writes are free, but reads get slower
+/// the more users are followed. Mostly there to compare against.
+pub struct ReadStore;
+
+impl TimelineStore for ReadStore {
+    fn insert<'a>(
+        &'a self,
+        _: &'a mut sqlx::PgConnection,
+        _: i64,
+        _: i64,
+        _: i64,
+    ) -> BoxFuture<'a, ()> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn follow<'a>(&'a self, _: &'a mut sqlx::PgConnection, _: i64, _: i64) -> BoxFuture<'a, ()> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn unfollow<'a>(&'a self, _: &'a mut sqlx::PgConnection, _: i64, _: i64) -> BoxFuture<'a, ()> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn page<'a>(
+        &'a self,
+        database: &'a Database,
+        user_id: i64,
+        cursor: Cursor,
+        limit: i32,
+    ) -> BoxFuture<'a, Vec<Cursor>> {
+        Box::pin(async move {
+            let rows = sqlx::query("SELECT tweets.id, tweets.time_created FROM tweets JOIN users ON users.id = tweets.user_id WHERE (tweets.user_id = $1 OR tweets.user_id IN (SELECT followee_id FROM follows WHERE follower_id = $1)) AND (tweets.time_created, tweets.id) < ($2, $3) AND users.time_deactivated IS NULL AND users.time_suspended IS NULL ORDER BY tweets.time_created DESC, tweets.id DESC LIMIT $4")
+                .bind(user_id)
+                .bind(cursor.time)
+                .bind(cursor.id)
+                .bind(limit)
+                .fetch_all(database)
+                .await?;
+
+            Ok(rows
+                .into_iter()
+                .map(|row| Cursor {
+                    time: row.get_unchecked(1),
+                    id: row.get_unchecked(0),
+                })
+                .collect())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_kind() {
+        use crate::timeline::Kind;
+
+        assert_eq!("hybrid".parse(), Ok(Kind::Hybrid));
+        assert_eq!("read".parse(), Ok(Kind::Read));
+        assert_eq!("write".parse::<Kind>(), Err(()));
+    }
+}
@@ -0,0 +1,69 @@
+use crate::common::*;
+
+/// Returns the tweets that can be seen, with their authors, in no particular order. Tweets of
+/// deactivated and suspended users can't, just as their profiles. Whether the user liked them
+/// is only known with a session.
+pub async fn fetch(
+    database: &Database,
+    ids: &[i64],
+    session_id: Option<i64>,
+) -> std::result::Result<Vec<(i64, Tweet)>, sqlx::Error> {
+    let rows = sqlx::query(&format!("SELECT {}, EXISTS (SELECT FROM user_liked_tweets WHERE user_id = (SELECT user_id FROM sessions WHERE id = $2) AND tweet_id = tweets.id), {} FROM tweets JOIN users ON users.id = tweets.user_id WHERE tweets.id = ANY($1) AND users.time_deactivated IS NULL AND users.time_suspended IS NULL", Tweet::COLUMNS, User::COLUMNS))
+        .bind(ids)
+        .bind(session_id)
+        .fetch_all(database)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let mut tweet = Tweet::from_row(&row, 0).with_author(User::from_row(&row, 12));
+
+            if session_id.is_some() {
+                tweet = tweet.with_liked_by_me(row.get_unchecked(11));
+            }
+
+            (row.get_unchecked(0), tweet)
+        })
+        .collect())
+}
+
+/// Like `fetch`, but keeps the order of `ids` and leaves out the tweets that can't be seen.
+pub async fn fetch_ordered(
+    database: &Database,
+    ids: &[i64],
+    session_id: Option<i64>,
+) -> std::result::Result<Vec<Tweet>, sqlx::Error> {
+    let mut found = fetch(database, ids, session_id)
+        .await?
+        .into_iter()
+        .collect::<std::collections::HashMap<i64, Tweet>>();
+
+    Ok(ids.iter().filter_map(|id| found.remove(id)).collect())
+}
+
+/// Hangs the replies below the tweet, `depth` levels deep. `replies` lists the IDs of the replies
+/// of each tweet in order. Replies that weren't found are left out, along with their own replies.
+pub fn nest(
+    id: i64,
+    tweet: Tweet,
+    replies: &std::collections::HashMap<i64, Vec<i64>>,
+    found: &mut std::collections::HashMap<i64, Tweet>,
+    depth: i32,
+) -> Tweet {
+    if depth == 0 {
+        return tweet;
+    }
+
+    let nested = replies
+        .get(&id)
+        .into_iter()
+        .flatten()
+        .filter_map(|id| {
+            let reply = found.remove(id)?;
+            Some(nest(*id, reply, replies, found, depth - 1))
+        })
+        .collect();
+
+    tweet.with_replies(nested)
+}
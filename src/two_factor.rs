@@ -0,0 +1,254 @@
+use crate::common::Database;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use sha2::Digest;
+use sqlx::Row;
+
+static TOTP_ISSUER: Lazy<String> =
+    Lazy::new(|| std::env::var("TOTP_ISSUER").unwrap_or(String::from("assessment")));
+
+const ALPHABET: base32::Alphabet = base32::Alphabet::RFC4648 { padding: false };
+
+// https://www.rfc-editor.org/rfc/rfc6238#section-4
+const PERIOD: u64 = 30;
+const DIGITS: u32 = 6;
+
+/// How many periods a code is accepted for before and after its own one, to tolerate clock drift.
+const SKEW: i64 = 1;
+
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Two-factor logins must be completed within that many seconds after the password check.
+pub const CHALLENGE_LIFETIME: i64 = 5 * 60;
+
+/// A challenge is revoked after that many wrong codes.
+pub const CHALLENGE_MAX_ATTEMPTS: i32 = 5;
+
+/// Older challenges of the user are revoked when a new one would exceed that many. Wrong codes
+/// count as failed logins no matter the challenge, this only keeps the table small.
+pub const MAX_OPEN_CHALLENGES: i64 = 3;
+
+#[inline(always)]
+pub fn generate_secret() -> String {
+    // 160 bits, as recommended for HMAC-SHA1
+    let mut secret = [0u8; 20];
+    OsRng.fill_bytes(&mut secret);
+    base32::encode(ALPHABET, &secret)
+}
+
+pub fn provisioning_uri(username: &str, secret: &str) -> String {
+    // https://github.com/google/google-authenticator/wiki/Key-Uri-Format
+    format!(
+        "otpauth://totp/{issuer}:{username}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+        issuer = percent_encode(&TOTP_ISSUER),
+        username = percent_encode(username),
+        secret = secret,
+        digits = DIGITS,
+        period = PERIOD,
+    )
+}
+
+fn percent_encode(val: &str) -> String {
+    let mut encoded = String::with_capacity(val.len());
+
+    for byte in val.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    encoded
+}
+
+#[inline(always)]
+pub fn current_step() -> i64 {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap();
+
+    (now.as_secs() / PERIOD) as i64
+}
+
+/// https://www.rfc-editor.org/rfc/rfc4226#section-5.3
+pub fn hotp(secret: &[u8], counter: u64, digits: u32) -> u32 {
+    let mut mac = Hmac::<sha1::Sha1>::new_from_slice(secret).unwrap();
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let code = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+
+    code % 10u32.pow(digits)
+}
+
+/// Checks the code against the base32 encoded secret. On success, returns the time step
+/// the code belongs to. Codes of `last_step` and earlier are rejected, so that every code
+/// can only be used once.
+pub fn verify_totp(secret: &str, code: &str, step: i64, last_step: Option<i64>) -> Option<i64> {
+    if code.len() != DIGITS as usize || !code.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+
+    let code = code.parse::<u32>().ok()?;
+    let secret = base32::decode(ALPHABET, secret)?;
+
+    (step - SKEW..=step + SKEW)
+        .filter(|&candidate| candidate >= 0 && Some(candidate) > last_step)
+        .find(|&candidate| hotp(&secret, candidate as u64, DIGITS) == code)
+}
+
+/// Returns a random code like `ABCDE-FGHIJ`.
+pub fn generate_recovery_code() -> String {
+    let mut bytes = [0u8; 7];
+    OsRng.fill_bytes(&mut bytes);
+
+    let mut code = base32::encode(ALPHABET, &bytes);
+    code.truncate(10);
+    code.insert(5, '-');
+    code
+}
+
+#[inline(always)]
+pub fn is_recovery_code(code: &str) -> bool {
+    code.len() == 11
+}
+
+/// Recovery codes are random enough to not need a slow hash (unlike passwords).
+pub fn hash_recovery_code(code: &str) -> String {
+    let code = code.trim().to_ascii_uppercase().replace('-', "");
+    base64::encode(sha2::Sha256::digest(code.as_bytes()))
+}
+
+/// Starts a two-factor login of the user and returns the challenge token, the login
+/// is completed by `sessions::two_factor::post`.
+pub async fn create_challenge(database: &Database, user_id: i64) -> Result<String, sqlx::Error> {
+    let row = sqlx::query("INSERT INTO two_factor_challenges (user_id) VALUES ($1) RETURNING id")
+        .bind(user_id)
+        .fetch_one(database)
+        .await?;
+
+    sqlx::query("DELETE FROM two_factor_challenges WHERE user_id = $1 AND id NOT IN (SELECT id FROM two_factor_challenges WHERE user_id = $1 ORDER BY id DESC LIMIT $2)")
+        .bind(user_id)
+        .bind(MAX_OPEN_CHALLENGES)
+        .execute(database)
+        .await?;
+
+    let challenge_id = row.get_unchecked::<i64, _>(0).to_le_bytes();
+
+    Ok(crate::auth::create_challenge_token(&challenge_id))
+}
+
+/// Checks a TOTP or recovery code of the user. Either of them can only be used once.
+pub async fn verify_code(
+    connection: &mut sqlx::PgConnection,
+    user_id: i64,
+    code: &str,
+) -> Result<bool, sqlx::Error> {
+    if is_recovery_code(code) {
+        let result =
+            sqlx::query("DELETE FROM recovery_codes WHERE user_id = $1 AND code_hash = $2")
+                .bind(user_id)
+                .bind(hash_recovery_code(code))
+                .execute(&mut *connection)
+                .await?;
+
+        return Ok(result.rows_affected() != 0);
+    }
+
+    let row = sqlx::query("SELECT totp_secret, totp_last_step FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(&mut *connection)
+        .await?;
+
+    let row = match row {
+        Some(row) => row,
+        None => {
+            return Ok(false);
+        }
+    };
+
+    let secret = match row.get_unchecked::<Option<&str>, _>(0) {
+        Some(secret) => secret,
+        None => {
+            return Ok(false);
+        }
+    };
+
+    let step = match verify_totp(secret, code, current_step(), row.get_unchecked(1)) {
+        Some(step) => step,
+        None => {
+            return Ok(false);
+        }
+    };
+
+    // concurrent requests with the same code must not both succeed
+    let result = sqlx::query("UPDATE users SET totp_last_step = $1 WHERE id = $2 AND (totp_last_step IS NULL OR totp_last_step < $1)")
+        .bind(step)
+        .bind(user_id)
+        .execute(&mut *connection)
+        .await?;
+
+    Ok(result.rows_affected() != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::two_factor::*;
+
+    #[test]
+    fn test_hotp() {
+        // https://www.rfc-editor.org/rfc/rfc6238#appendix-B
+        let secret = b"12345678901234567890";
+
+        assert_eq!(hotp(secret, 59 / 30, 8), 94287082);
+        assert_eq!(hotp(secret, 1111111109 / 30, 8), 7081804);
+        assert_eq!(hotp(secret, 1234567890 / 30, 8), 89005924);
+        assert_eq!(hotp(secret, 20000000000 / 30, 8), 65353130);
+    }
+
+    #[test]
+    fn test_verify_totp() {
+        let secret = base32::encode(ALPHABET, b"12345678901234567890");
+        let step = 1234567890 / 30;
+
+        let code = format!("{:06}", hotp(b"12345678901234567890", step as u64, 6));
+
+        assert_eq!(verify_totp(&secret, &code, step, None), Some(step));
+        assert_eq!(verify_totp(&secret, &code, step + 1, None), Some(step));
+        assert_eq!(verify_totp(&secret, &code, step + 2, None), None);
+        assert_eq!(verify_totp(&secret, &code, step, Some(step)), None);
+        assert_eq!(verify_totp(&secret, "12345", step, None), None);
+        assert_eq!(verify_totp(&secret, "abcdef", step, None), None);
+    }
+
+    #[test]
+    fn test_recovery_code() {
+        let code = generate_recovery_code();
+
+        assert!(is_recovery_code(&code));
+        assert_eq!(
+            hash_recovery_code(&code),
+            hash_recovery_code(&code.to_ascii_lowercase())
+        );
+        assert_ne!(
+            hash_recovery_code(&code),
+            hash_recovery_code(&generate_recovery_code())
+        );
+    }
+
+    #[test]
+    fn test_provisioning_uri() {
+        assert_eq!(
+            provisioning_uri("hello world", "ABC"),
+            "otpauth://totp/assessment:hello%20world?secret=ABC&issuer=assessment&algorithm=SHA1&digits=6&period=30"
+        );
+    }
+}
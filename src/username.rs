@@ -0,0 +1,139 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use once_cell::sync::Lazy;
+use sqlx::Row;
+use unicode_normalization::UnicodeNormalization;
+
+pub const MIN_LENGTH: usize = 3;
+pub const MAX_LENGTH: usize = 32;
+
+/// How long a user has to wait between username changes, in seconds.
+pub static CHANGE_COOLDOWN: Lazy<i64> =
+    Lazy::new(|| crate::env_param!("USERNAME_CHANGE_COOLDOWN_DAYS", 30i64).max(0) * 24 * 60 * 60);
+
+/// How long an old username is held back from everybody but its previous owner, in seconds,
+/// so that nobody can impersonate a user right after they renamed themselves.
+pub static HOLD_PERIOD: Lazy<i64> =
+    Lazy::new(|| crate::env_param!("USERNAME_HOLD_DAYS", 90i64).max(0) * 24 * 60 * 60);
+
+/// Compared case-insensitively. Names like `@me` are not in here, since the character
+/// set doesn't allow them anyway.
+static RESERVED_USERNAMES: Lazy<Vec<String>> = Lazy::new(|| {
+    std::env::var("RESERVED_USERNAMES")
+        .unwrap_or(String::from(
+            "admin,administrator,root,system,support,help,moderator,staff,official,security,api,me,users,tweets,null,undefined",
+        ))
+        .split(',')
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect()
+});
+
+/// NFKC folds compatibility characters (like fullwidth letters or ligatures) into their
+/// plain forms, so that they can't be used to make look-alikes of other usernames.
+#[inline(always)]
+pub fn normalize(username: &str) -> String {
+    username.nfkc().collect()
+}
+
+/// Decodes a percent-encoded path segment and normalizes it, or returns `None` if it's
+/// not valid UTF-8.
+pub fn from_path(segment: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut rest = segment.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' && tail.len() >= 2 {
+            if !tail[..2].iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+
+            // both are hex digits, so they are valid UTF-8 and a valid number
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&tail[..2]).unwrap(), 16).unwrap());
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    Some(normalize(&String::from_utf8(bytes).ok()?))
+}
+
+/// Whether a normalized username is acceptable for a new account. Only ASCII letters, digits
+/// and underscores are allowed, which rules out look-alikes from other scripts as well.
+pub fn is_invalid(username: &str) -> bool {
+    username.len() < MIN_LENGTH
+        || username.len() > MAX_LENGTH
+        || !username
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+}
+
+#[inline(always)]
+pub fn is_reserved(username: &str) -> bool {
+    RESERVED_USERNAMES.contains(&username.to_lowercase())
+}
+
+/// Whether the username was given up by somebody other than the user within the hold period.
+pub async fn is_held<'a>(
+    executor: impl sqlx::PgExecutor<'a>,
+    username: &str,
+    user_id: Option<i64>,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query("SELECT EXISTS (SELECT FROM username_history WHERE lower(username) = lower($1) AND user_id IS DISTINCT FROM $2 AND time_changed > extract(EPOCH FROM now()) - $3)")
+        .bind(username)
+        .bind(user_id)
+        .bind(*HOLD_PERIOD)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(row.get_unchecked::<bool, _>(0))
+}
+
+/// Returns a random username like `user_1234567890`, for accounts that don't come with one.
+#[inline(always)]
+pub fn generate() -> String {
+    format!("user_{:010}", OsRng.next_u64() % 10_000_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::username::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("Ｈｅｌｌｏ"), "Hello");
+        assert_eq!(normalize("ﬁsh"), "fish");
+        assert_eq!(normalize("hello"), "hello");
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(from_path("hello").as_deref(), Some("hello"));
+        assert_eq!(from_path("%EF%BC%A8ello").as_deref(), Some("Hello"));
+        assert_eq!(from_path("j%C3%BCrgen").as_deref(), Some("jürgen"));
+        assert_eq!(from_path("%FF"), None);
+        assert_eq!(from_path("%zz"), None);
+        assert_eq!(from_path("%+f"), None);
+    }
+
+    #[test]
+    fn test_is_invalid() {
+        assert!(!is_invalid("hello"));
+        assert!(!is_invalid("Hello_World_42"));
+        assert!(is_invalid("he"));
+        assert!(is_invalid(&"a".repeat(33)));
+        assert!(is_invalid("@me"));
+        assert!(is_invalid("hello world"));
+        assert!(is_invalid("hеllo")); // cyrillic е
+        assert!(!is_invalid(&normalize("Ｈｅｌｌｏ")));
+        assert!(!is_invalid(&generate()));
+    }
+
+    #[test]
+    fn test_is_reserved() {
+        assert!(is_reserved("admin"));
+        assert!(is_reserved("Admin"));
+        assert!(!is_reserved("admins"));
+    }
+}